@@ -24,6 +24,8 @@ use std::{
 };
 
 use cargo_metadata::{MetadataCommand, Package};
+use risc0_zkvm_platform::memory::{self, Region};
+pub use risc0_zkvm_platform::memory::{Layout, Sizes};
 use risc0_zkvm_platform_sys::LINKER_SCRIPT;
 use risc0_zkvm_sys::{make_method_id_from_elf, MethodId, METHOD_ID_LEN};
 use serde::Deserialize;
@@ -41,6 +43,91 @@ impl Risc0Metadata {
     }
 }
 
+// The sizes under [package.metadata.risc0.layout] in a guest package, each
+// as a number of bytes or a string such as "64K" or "1M".
+#[derive(Debug, Default, Deserialize)]
+struct LayoutMetadata {
+    stack: Option<Size>,
+    heap: Option<Size>,
+    input: Option<Size>,
+    output: Option<Size>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Size {
+    Bytes(usize),
+    Text(String),
+}
+
+impl Size {
+    fn bytes(&self) -> usize {
+        match self {
+            Size::Bytes(bytes) => *bytes,
+            Size::Text(text) => parse_size(text).unwrap_or_else(|| {
+                eprintln!("ERROR: Invalid size in package.metadata.risc0.layout: {text}");
+                std::process::exit(-1);
+            }),
+        }
+    }
+}
+
+fn parse_size(text: &str) -> Option<usize> {
+    let text = text.trim();
+    let (digits, scale) = if let Some(digits) = text.strip_suffix('K') {
+        (digits, 1024)
+    } else if let Some(digits) = text.strip_suffix('M') {
+        (digits, 1024 * 1024)
+    } else {
+        (text, 1)
+    };
+    digits.trim().parse::<usize>().ok()?.checked_mul(scale)
+}
+
+/// Returns the memory layout given in the package's
+/// [package.metadata.risc0.layout], with the default size for any region it
+/// leaves out.
+fn guest_layout(pkg: &Package) -> Layout {
+    let metadata: LayoutMetadata = pkg
+        .metadata
+        .get("risc0")
+        .and_then(|risc0| risc0.get("layout"))
+        .map(|layout| serde_json::from_value(layout.clone()).unwrap())
+        .unwrap_or_default();
+    let size = |size: &Option<Size>, default: usize| size.as_ref().map_or(default, Size::bytes);
+    let sizes = Sizes {
+        stack: size(&metadata.stack, Sizes::DEFAULT.stack),
+        heap: size(&metadata.heap, Sizes::DEFAULT.heap),
+        input: size(&metadata.input, Sizes::DEFAULT.input),
+        output: size(&metadata.output, Sizes::DEFAULT.output),
+    };
+    Layout::new(sizes).unwrap_or_else(|err| {
+        eprintln!("ERROR: Invalid memory layout for {}: {}", pkg.name, err);
+        std::process::exit(-1);
+    })
+}
+
+// The C, C++ and assembly sources under [package.metadata.risc0.cc] in a
+// guest package, and the directories their headers are in, relative to the
+// package.
+#[derive(Debug, Default, Deserialize)]
+struct CcMetadata {
+    #[serde(default)]
+    sources: Vec<String>,
+    #[serde(default)]
+    include: Vec<String>,
+}
+
+impl CcMetadata {
+    fn from_package(pkg: &Package) -> CcMetadata {
+        pkg.metadata
+            .get("risc0")
+            .and_then(|risc0| risc0.get("cc"))
+            .map(|cc| serde_json::from_value(cc.clone()).unwrap())
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Debug)]
 struct Risc0Method {
     name: String,
@@ -247,12 +334,172 @@ pub fn embed_methods() {
     println!("cargo:rerun-if-changed={}", methods_path.display());
 }
 
+// Returns the platform linker script with its MEMORY block generated from
+// the given layout and the fixed regions in risc0_zkvm_platform.
+fn linker_script(layout: &Layout) -> String {
+    let regions: [(&str, &str, Region); 9] = [
+        ("stack", "", layout.stack),
+        ("data", "(RW)", layout.data),
+        ("heap", "", layout.heap),
+        ("input", "", layout.input),
+        ("gpio", "", memory::GPIO),
+        ("prog", "(X)", memory::PROG),
+        ("sha", "", memory::SHA),
+        ("output", "", layout.output),
+        ("commit", "", layout.commit),
+    ];
+    let mut block = String::from("MEMORY {\n");
+    for (name, attrs, region) in regions {
+        block += &format!(
+            "  {:<8}{:<5}: ORIGIN = 0x{:08X}, LENGTH = 0x{:08X}\n",
+            name,
+            attrs,
+            region.start(),
+            region.len()
+        );
+    }
+    block += "}";
+
+    let script = std::str::from_utf8(LINKER_SCRIPT).unwrap();
+    let begin = script
+        .find("MEMORY {")
+        .expect("risc0.ld has no MEMORY block");
+    let end = begin + script[begin..].find('}').unwrap() + 1;
+    format!("{}{}{}", &script[..begin], block, &script[end..])
+}
+
 /// Called inside the guest crate's build.rs to do special linking for the ZKVM
+///
+/// The guest crate can trade the sizes of its stack, heap, input and output
+/// against each other in its Cargo.toml; see [Sizes] for the limits. Regions
+/// which are left out keep their default sizes.
+///
+/// ```toml
+/// [package.metadata.risc0.layout]
+/// stack = "64K"
+/// heap = "1344K"
+/// input = "128K"
+/// ```
+///
+/// It also compiles the C, C++ and assembly sources the guest crate lists in
+/// its Cargo.toml with [cc_build], and links them into its methods.
+///
+/// ```toml
+/// [package.metadata.risc0.cc]
+/// sources = ["ref10/fe.c", "ref10/ge.c", "ref10/sc.c"]
+/// include = ["ref10"]
+/// ```
 pub fn link() {
+    if env::var("CARGO_CFG_TARGET_ARCH").unwrap() == "riscv32" {
+        let pkg = current_package();
+        compile_guest_cc(&pkg);
+        link_with_layout(guest_layout(&pkg));
+    }
+}
+
+/// Returns a [cc::Build] set up to compile C, C++ and assembly for the ZKVM,
+/// with the flags of the RISC-V toolchain used by the Bazel build.
+///
+/// If `RISC0_TOOLCHAIN` is set to the directory of a RISC-V toolchain, such
+/// as a release of <https://github.com/risc0/toolchain>, its compiler and C
+/// library headers are used. Otherwise, cc looks for `riscv32-unknown-elf-gcc`
+/// as usual, which can be overridden with `CC_riscv32im_unknown_none_elf`.
+///
+/// The guest runtime provides `memcpy`, `memmove`, `memset` and `memcmp`;
+/// code that needs more of the C library has to supply it.
+///
+/// ```no_run
+/// // In the guest crate's build.rs
+/// risc0_build::cc_build()
+///     .file("c/keccak.c")
+///     .define("KECCAK_UNROLL", "1")
+///     .compile("keccak");
+/// risc0_build::link();
+/// ```
+pub fn cc_build() -> cc::Build {
+    let mut build = cc::Build::new();
+    if let Some(toolchain) = env::var_os("RISC0_TOOLCHAIN") {
+        let toolchain = Path::new(&toolchain);
+        build
+            .compiler(toolchain.join("bin").join("riscv32-unknown-elf-gcc"))
+            .archiver(toolchain.join("bin").join("riscv32-unknown-elf-ar"))
+            .include(toolchain.join("picolibc").join("include"));
+    }
+    println!("cargo:rerun-if-env-changed=RISC0_TOOLCHAIN");
+    build
+        .define("RISCV", "1")
+        .flag("-march=rv32im")
+        .flag("-mabi=ilp32")
+        .flag("-ffreestanding")
+        .flag("-fno-strict-aliasing")
+        .flag("-fno-exceptions")
+        .flag("-fdata-sections")
+        .flag("-ffunction-sections");
+    build
+}
+
+// Compiles the sources listed in the guest package's metadata into static
+// libraries, one for C and assembly and one for C++, which the methods link
+// against.
+fn compile_guest_cc(pkg: &Package) {
+    let metadata = CcMetadata::from_package(pkg);
+    if metadata.sources.is_empty() {
+        return;
+    }
+
+    // Once a build script names the files it depends on, cargo stops
+    // rerunning it for other changes, so name the manifest too.
+    let manifest_dir = pkg.manifest_path.parent().unwrap();
+    println!("cargo:rerun-if-changed={}", pkg.manifest_path);
+
+    let (cxx_sources, c_sources): (Vec<&String>, Vec<&String>) = metadata
+        .sources
+        .iter()
+        .partition(|source| is_cxx_source(source));
+    for (sources, cpp, name) in [
+        (c_sources, false, "risc0_guest_c"),
+        (cxx_sources, true, "risc0_guest_cxx"),
+    ] {
+        if sources.is_empty() {
+            continue;
+        }
+        let mut build = cc_build();
+        build.cpp(cpp).cpp_link_stdlib(None);
+        if cpp {
+            build
+                .flag("-fno-rtti")
+                .flag("-fno-threadsafe-statics")
+                .flag("-fno-use-cxa-atexit");
+        }
+        for include in &metadata.include {
+            let path = manifest_dir.join(include);
+            println!("cargo:rerun-if-changed={}", path);
+            build.include(path);
+        }
+        for source in sources {
+            let path = manifest_dir.join(source);
+            println!("cargo:rerun-if-changed={}", path);
+            build.file(path);
+        }
+        build.compile(name);
+    }
+}
+
+fn is_cxx_source(source: &str) -> bool {
+    let ext = Path::new(source).extension().and_then(|ext| ext.to_str());
+    matches!(ext, Some("cc" | "cpp" | "cxx"))
+}
+
+/// Like [link], but with the given memory layout rather than the one in the
+/// guest crate's Cargo.toml.
+///
+/// The layout is linked into the method, where the guest and the host's
+/// prover both find it, and so is covered by the method ID.
+pub fn link_with_layout(layout: Layout) {
     if env::var("CARGO_CFG_TARGET_ARCH").unwrap() == "riscv32" {
         let out_dir = env::var_os("OUT_DIR").unwrap();
         let linker_script = Path::new(&out_dir).join("risc0.ld");
-        fs::write(&linker_script, LINKER_SCRIPT).unwrap();
+        fs::write(&linker_script, self::linker_script(&layout)).unwrap();
         println!("cargo:rustc-link-arg=-T{}", linker_script.to_str().unwrap());
     }
 }
@@ -20,6 +20,7 @@ extern crate alloc;
 
 pub mod fp;
 pub mod fp4;
+pub mod merkle;
 pub mod ntt;
 pub mod poly;
 pub mod rou;
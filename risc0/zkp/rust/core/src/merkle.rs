@@ -0,0 +1,553 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Merkle trees and sparse Merkle maps built on [Sha].
+//!
+//! Everything here is generic over [Sha], so the same code (and the same
+//! roots) can be used by the host with the CPU implementation and by a guest
+//! with the accelerated implementation. Interior nodes are always computed
+//! with [Sha::hash_pair], and trees use the same layout as the verifier's
+//! Merkle tree: the root is at index 1 and the children of the node at index
+//! `i` are at `2 * i` and `2 * i + 1`.
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec,
+    vec::Vec,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::sha::{Digest, Sha, DIGEST_WORDS};
+
+/// The number of levels in a [SparseMerkleMap]; one per bit of the key.
+pub const SPARSE_DEPTH: usize = DIGEST_WORDS * 32;
+
+/// A binary Merkle tree over a list of leaf [Digests](Digest).
+///
+/// The number of leaves is padded up to a power of two with
+/// [Digest::default()] leaves.
+#[derive(Clone, Debug)]
+pub struct MerkleTree<S: Sha> {
+    sha: S,
+    row_size: usize,
+    nodes: Vec<Digest>,
+}
+
+/// A proof that a leaf is included in a [MerkleTree].
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MerkleProof {
+    /// The siblings of each node on the path from the leaf to the root,
+    /// starting at the leaf.
+    pub siblings: Vec<Digest>,
+}
+
+impl<S: Sha> MerkleTree<S> {
+    /// Construct a new [MerkleTree] from the given leaves.
+    pub fn new(sha: &S, leaves: &[Digest]) -> Self {
+        let row_size = leaves.len().max(1).next_power_of_two();
+        let mut nodes = vec![Digest::default(); row_size * 2];
+        nodes[row_size..row_size + leaves.len()].clone_from_slice(leaves);
+        for i in (1..row_size).rev() {
+            nodes[i] = *sha.hash_pair(&nodes[2 * i], &nodes[2 * i + 1]);
+        }
+        MerkleTree {
+            sha: sha.clone(),
+            row_size,
+            nodes,
+        }
+    }
+
+    /// Returns the root hash of the tree.
+    pub fn root(&self) -> &Digest {
+        &self.nodes[1]
+    }
+
+    /// Returns the number of leaves in the tree, including padding.
+    pub fn row_size(&self) -> usize {
+        self.row_size
+    }
+
+    /// Returns the leaf at the given index.
+    pub fn leaf(&self, idx: usize) -> &Digest {
+        assert!(idx < self.row_size);
+        &self.nodes[self.row_size + idx]
+    }
+
+    /// Returns a proof that the leaf at the given index is in the tree.
+    pub fn prove(&self, idx: usize) -> MerkleProof {
+        assert!(idx < self.row_size);
+        let mut siblings = Vec::new();
+        let mut idx = idx + self.row_size;
+        while idx > 1 {
+            siblings.push(self.nodes[idx ^ 1]);
+            idx /= 2;
+        }
+        MerkleProof { siblings }
+    }
+
+    /// Replace the leaf at the given index, and update the root.
+    pub fn update(&mut self, idx: usize, leaf: &Digest) {
+        self.update_batch(&[(idx, *leaf)]);
+    }
+
+    /// Replace a set of leaves, and update the root.
+    ///
+    /// Each interior node is rehashed at most once, no matter how many of the
+    /// updated leaves lie below it.
+    pub fn update_batch(&mut self, updates: &[(usize, Digest)]) {
+        let mut dirty = BTreeSet::new();
+        for (idx, leaf) in updates {
+            assert!(*idx < self.row_size);
+            let idx = self.row_size + idx;
+            self.nodes[idx] = *leaf;
+            dirty.insert(idx / 2);
+        }
+        while !dirty.is_empty() {
+            let mut parents = BTreeSet::new();
+            for idx in dirty.into_iter().filter(|idx| *idx > 0) {
+                self.nodes[idx] = *self
+                    .sha
+                    .hash_pair(&self.nodes[2 * idx], &self.nodes[2 * idx + 1]);
+                parents.insert(idx / 2);
+            }
+            dirty = parents;
+        }
+    }
+}
+
+impl MerkleProof {
+    /// Computes the root implied by this proof for the given leaf and index.
+    pub fn root<S: Sha>(&self, sha: &S, idx: usize, leaf: &Digest) -> Digest {
+        let mut cur = *leaf;
+        let mut idx = idx;
+        for sibling in self.siblings.iter() {
+            // The low bit determines whether cur is the left (0) or right (1) child.
+            if idx % 2 == 1 {
+                cur = *sha.hash_pair(sibling, &cur);
+            } else {
+                cur = *sha.hash_pair(&cur, sibling);
+            }
+            idx /= 2;
+        }
+        cur
+    }
+
+    /// Verifies that the given leaf is at the given index in a tree with the
+    /// given root and [row size](MerkleTree::row_size).
+    ///
+    /// Leaves and interior nodes are hashed alike, so the proof must have one
+    /// sibling per level of the tree; otherwise an interior node could be
+    /// passed off as a leaf of a shallower tree.
+    pub fn verify<S: Sha>(
+        &self,
+        sha: &S,
+        root: &Digest,
+        row_size: usize,
+        idx: usize,
+        leaf: &Digest,
+    ) -> bool {
+        row_size.is_power_of_two()
+            && self.siblings.len() == row_size.trailing_zeros() as usize
+            && idx < row_size
+            && self.root(sha, idx, leaf) == *root
+    }
+}
+
+/// A proof about the presence or absence of a key in a [SparseMerkleMap].
+///
+/// Siblings which are the root of an empty subtree are omitted, and marked
+/// in `empty`, so that proofs for lightly populated maps stay small.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SparseMerkleProof {
+    /// A bitmap with one bit per level, starting at the leaf; a set bit means
+    /// the sibling at that level is empty.
+    pub empty: [u32; DIGEST_WORDS],
+    /// The non-empty siblings on the path from the leaf to the root, starting
+    /// at the leaf.
+    pub siblings: Vec<Digest>,
+}
+
+/// Verifies [SparseMerkleProofs](SparseMerkleProof) against a root.
+///
+/// This holds the hashes of empty subtrees, so that they are only computed
+/// once when checking many proofs.
+#[derive(Clone, Debug)]
+pub struct SparseMerkleVerifier<S: Sha> {
+    sha: S,
+    // empty[h] is the root of an empty subtree of height h.
+    empty: Vec<Digest>,
+}
+
+impl<S: Sha> SparseMerkleVerifier<S> {
+    /// Construct a new [SparseMerkleVerifier].
+    pub fn new(sha: &S) -> Self {
+        let mut empty = Vec::with_capacity(SPARSE_DEPTH + 1);
+        empty.push(Digest::default());
+        for h in 0..SPARSE_DEPTH {
+            let next = *sha.hash_pair(&empty[h], &empty[h]);
+            empty.push(next);
+        }
+        SparseMerkleVerifier {
+            sha: sha.clone(),
+            empty,
+        }
+    }
+
+    /// Returns the root of an empty [SparseMerkleMap].
+    pub fn empty_root(&self) -> &Digest {
+        &self.empty[SPARSE_DEPTH]
+    }
+
+    /// Returns the leaf stored for a key/value pair.
+    pub fn leaf(&self, key: &Digest, value: &Digest) -> Digest {
+        *self.sha.hash_pair(key, value)
+    }
+
+    /// Computes the root implied by the given proof, when `key` holds `value`
+    /// (or is absent, if `value` is `None`). Returns `None` if the proof is
+    /// malformed, with too few or too many siblings.
+    ///
+    /// Together with [verify_inclusion](Self::verify_inclusion) or
+    /// [verify_non_inclusion](Self::verify_non_inclusion) against the old
+    /// root, this lets a verifier which only holds a root apply an update.
+    pub fn root(
+        &self,
+        proof: &SparseMerkleProof,
+        key: &Digest,
+        value: Option<&Digest>,
+    ) -> Option<Digest> {
+        let mut cur = match value {
+            Some(value) => self.leaf(key, value),
+            None => self.empty[0],
+        };
+        let mut siblings = proof.siblings.iter();
+        for h in 0..SPARSE_DEPTH {
+            let sibling = if get_bit(&proof.empty, h) {
+                &self.empty[h]
+            } else {
+                siblings.next()?
+            };
+            if get_bit(key.get(), SPARSE_DEPTH - 1 - h) {
+                cur = *self.sha.hash_pair(sibling, &cur);
+            } else {
+                cur = *self.sha.hash_pair(&cur, sibling);
+            }
+        }
+        match siblings.next() {
+            Some(_) => None,
+            None => Some(cur),
+        }
+    }
+
+    /// Verifies that `key` holds `value` in a map with the given root.
+    pub fn verify_inclusion(
+        &self,
+        root: &Digest,
+        proof: &SparseMerkleProof,
+        key: &Digest,
+        value: &Digest,
+    ) -> bool {
+        self.root(proof, key, Some(value)).as_ref() == Some(root)
+    }
+
+    /// Verifies that `key` is absent from a map with the given root.
+    pub fn verify_non_inclusion(
+        &self,
+        root: &Digest,
+        proof: &SparseMerkleProof,
+        key: &Digest,
+    ) -> bool {
+        self.root(proof, key, None).as_ref() == Some(root)
+    }
+}
+
+/// A sparse Merkle tree mapping 256-bit keys to [Digest] values.
+///
+/// Every possible key has a leaf, at the position given by the bits of the
+/// key (most significant bit of the first word first). A present key has the
+/// leaf `hash_pair(key, value)`, and an absent key has the leaf
+/// [Digest::default()], which allows proving both inclusion and
+/// non-inclusion.
+#[derive(Clone, Debug)]
+pub struct SparseMerkleMap<S: Sha> {
+    verifier: SparseMerkleVerifier<S>,
+    values: BTreeMap<Digest, Digest>,
+    // Non-empty nodes, indexed by height and by the key prefix leading to them.
+    nodes: BTreeMap<(usize, Digest), Digest>,
+}
+
+impl<S: Sha> SparseMerkleMap<S> {
+    /// Construct a new, empty [SparseMerkleMap].
+    pub fn new(sha: &S) -> Self {
+        SparseMerkleMap {
+            verifier: SparseMerkleVerifier::new(sha),
+            values: BTreeMap::new(),
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    /// Returns a verifier which uses the same [Sha] as this map.
+    pub fn verifier(&self) -> &SparseMerkleVerifier<S> {
+        &self.verifier
+    }
+
+    /// Returns the root hash of the map.
+    pub fn root(&self) -> &Digest {
+        self.node(SPARSE_DEPTH, &Digest::default())
+    }
+
+    /// Returns the number of keys in the map.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns true if the map contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the value for the given key, if present.
+    pub fn get(&self, key: &Digest) -> Option<&Digest> {
+        self.values.get(key)
+    }
+
+    /// Sets the value for the given key, and updates the root.
+    pub fn insert(&mut self, key: &Digest, value: &Digest) {
+        self.update_batch(&[(*key, Some(*value))]);
+    }
+
+    /// Removes the given key, and updates the root.
+    pub fn remove(&mut self, key: &Digest) {
+        self.update_batch(&[(*key, None)]);
+    }
+
+    /// Applies a set of insertions (`Some`) and removals (`None`), and
+    /// updates the root.
+    ///
+    /// Each interior node is rehashed at most once, no matter how many of the
+    /// updated keys lie below it.
+    pub fn update_batch(&mut self, updates: &[(Digest, Option<Digest>)]) {
+        let mut dirty = BTreeSet::new();
+        for (key, value) in updates {
+            let leaf = match value {
+                Some(value) => {
+                    self.values.insert(*key, *value);
+                    self.verifier.leaf(key, value)
+                }
+                None => {
+                    self.values.remove(key);
+                    self.verifier.empty[0]
+                }
+            };
+            self.set_node(0, *key, leaf);
+            dirty.insert(prefix(key, SPARSE_DEPTH - 1));
+        }
+        for h in 1..=SPARSE_DEPTH {
+            let mut parents = BTreeSet::new();
+            for path in dirty {
+                let left = self.node(h - 1, &path);
+                let right = self.node(h - 1, &with_bit(&path, SPARSE_DEPTH - h));
+                let node = *self.verifier.sha.hash_pair(left, right);
+                if h < SPARSE_DEPTH {
+                    parents.insert(prefix(&path, SPARSE_DEPTH - h - 1));
+                }
+                self.set_node(h, path, node);
+            }
+            dirty = parents;
+        }
+    }
+
+    /// Returns a proof for the given key, which can be used with
+    /// [SparseMerkleVerifier::verify_inclusion] if the key is present or
+    /// [SparseMerkleVerifier::verify_non_inclusion] if it is absent.
+    pub fn prove(&self, key: &Digest) -> SparseMerkleProof {
+        let mut proof = SparseMerkleProof::default();
+        for h in 0..SPARSE_DEPTH {
+            let bit = SPARSE_DEPTH - 1 - h;
+            let path = prefix(key, bit + 1);
+            let sibling_path = if get_bit(key.get(), bit) {
+                prefix(&path, bit)
+            } else {
+                with_bit(&path, bit)
+            };
+            match self.nodes.get(&(h, sibling_path)) {
+                Some(sibling) => proof.siblings.push(*sibling),
+                None => set_bit(&mut proof.empty, h),
+            }
+        }
+        proof
+    }
+
+    fn node(&self, height: usize, path: &Digest) -> &Digest {
+        self.nodes
+            .get(&(height, *path))
+            .unwrap_or(&self.verifier.empty[height])
+    }
+
+    fn set_node(&mut self, height: usize, path: Digest, node: Digest) {
+        if node == self.verifier.empty[height] {
+            self.nodes.remove(&(height, path));
+        } else {
+            self.nodes.insert((height, path), node);
+        }
+    }
+}
+
+fn get_bit(words: &[u32; DIGEST_WORDS], bit: usize) -> bool {
+    (words[bit / 32] >> (31 - bit % 32)) & 1 == 1
+}
+
+fn set_bit(words: &mut [u32; DIGEST_WORDS], bit: usize) {
+    words[bit / 32] |= 1 << (31 - bit % 32);
+}
+
+// Returns the given key with its first `len` bits kept, and the rest cleared.
+fn prefix(key: &Digest, len: usize) -> Digest {
+    let mut out = Digest::default();
+    for (i, word) in out.get_mut().iter_mut().enumerate() {
+        let keep = len.saturating_sub(i * 32).min(32);
+        if keep > 0 {
+            *word = key.get()[i] & (u32::MAX << (32 - keep));
+        }
+    }
+    out
+}
+
+fn with_bit(path: &Digest, bit: usize) -> Digest {
+    let mut out = *path;
+    set_bit(out.get_mut(), bit);
+    out
+}
+
+#[allow(missing_docs)]
+pub mod testutil {
+    use alloc::vec::Vec;
+
+    use super::{MerkleTree, SparseMerkleMap};
+    use crate::sha::{Digest, Sha};
+
+    // Runs conformance test on a SHA implementation to make sure it
+    // produces the same Merkle roots as every other implementation.
+    pub fn test_merkle_impl<S: Sha>(sha: &S) {
+        test_merkle_tree(sha);
+        test_sparse_merkle_map(sha);
+    }
+
+    fn leaves<S: Sha>(sha: &S, count: u32) -> Vec<Digest> {
+        (0..count)
+            .map(|i| *sha.hash_bytes(&i.to_le_bytes()))
+            .collect()
+    }
+
+    fn test_merkle_tree<S: Sha>(sha: &S) {
+        let initial = leaves(sha, 5);
+        let mut tree = MerkleTree::new(sha, &initial);
+        assert_eq!(tree.row_size(), 8);
+        assert_eq!(
+            *tree.root(),
+            Digest::from_str("acad0619043bd1541487e4ccd52d74feabd08f3347878194e70e37893701d95a")
+        );
+        for (idx, leaf) in initial.iter().enumerate() {
+            let proof = tree.prove(idx);
+            assert!(proof.verify(sha, tree.root(), 8, idx, leaf));
+            assert!(!proof.verify(sha, tree.root(), 8, idx ^ 1, leaf));
+            assert!(!proof.verify(sha, tree.root(), 16, idx, leaf));
+        }
+
+        // An interior node isn't a leaf of a shallower tree.
+        let mut proof = tree.prove(4);
+        let node = *sha.hash_pair(&initial[4], &proof.siblings.remove(0));
+        assert_eq!(proof.root(sha, 2, &node), *tree.root());
+        assert!(!proof.verify(sha, tree.root(), 8, 2, &node));
+
+        // Updating leaves one by one or in a batch gives the same root as
+        // building the tree from scratch.
+        let mut batch = MerkleTree::new(sha, &initial);
+        let updated = leaves(sha, 8);
+        for (idx, leaf) in updated.iter().enumerate().skip(3) {
+            tree.update(idx, leaf);
+        }
+        let updates: Vec<(usize, Digest)> = (3..8).map(|idx| (idx, updated[idx])).collect();
+        batch.update_batch(&updates);
+        let expected = MerkleTree::new(sha, &updated);
+        assert_eq!(tree.root(), expected.root());
+        assert_eq!(batch.root(), expected.root());
+    }
+
+    fn test_sparse_merkle_map<S: Sha>(sha: &S) {
+        let keys = leaves(sha, 3);
+        let mut map = SparseMerkleMap::new(sha);
+        let verifier = map.verifier().clone();
+        assert_eq!(map.root(), verifier.empty_root());
+        let proof = map.prove(&keys[0]);
+        assert!(proof.siblings.is_empty());
+        assert!(verifier.verify_non_inclusion(map.root(), &proof, &keys[0]));
+
+        // Apply an update starting from only the old root and a proof.
+        let old_root = *map.root();
+        map.insert(&keys[0], &keys[1]);
+        assert_eq!(
+            verifier.root(&proof, &keys[0], Some(&keys[1])),
+            Some(*map.root())
+        );
+        assert_ne!(*map.root(), old_root);
+
+        map.insert(&keys[1], &keys[2]);
+        map.insert(&keys[2], &keys[0]);
+        assert_eq!(
+            *map.root(),
+            Digest::from_str("5eb706f8f01b635135c6c25bc2597aa71d1d45adf3333a8b2bcd9d8d72c0b0fb")
+        );
+        let proof = map.prove(&keys[1]);
+        assert!(verifier.verify_inclusion(map.root(), &proof, &keys[1], &keys[2]));
+        assert!(!verifier.verify_inclusion(map.root(), &proof, &keys[1], &keys[1]));
+        assert!(!verifier.verify_non_inclusion(map.root(), &proof, &keys[1]));
+
+        // Malformed proofs are rejected, even against a zero root.
+        let mut short = proof.clone();
+        short.siblings.pop();
+        assert_eq!(verifier.root(&short, &keys[1], Some(&keys[2])), None);
+        assert!(!verifier.verify_inclusion(&Digest::default(), &short, &keys[1], &keys[2]));
+        let mut long = proof.clone();
+        long.siblings.push(keys[0]);
+        assert_eq!(verifier.root(&long, &keys[1], Some(&keys[2])), None);
+        assert!(!verifier.verify_non_inclusion(&Digest::default(), &long, &keys[1]));
+
+        // A batch gives the same root as individual updates, and removing
+        // every key gives back the empty root.
+        let mut batch = SparseMerkleMap::new(sha);
+        batch.update_batch(&[
+            (keys[0], Some(keys[1])),
+            (keys[1], Some(keys[2])),
+            (keys[2], Some(keys[0])),
+        ]);
+        assert_eq!(batch.root(), map.root());
+        batch.update_batch(&[(keys[0], None), (keys[1], None), (keys[2], None)]);
+        assert!(batch.is_empty());
+        assert_eq!(batch.root(), verifier.empty_root());
+        let proof = batch.prove(&keys[2]);
+        assert!(verifier.verify_non_inclusion(batch.root(), &proof, &keys[2]));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sha_cpu::Impl;
+
+    #[test]
+    fn test_impl() {
+        super::testutil::test_merkle_impl(&Impl {})
+    }
+}
@@ -35,7 +35,7 @@ use crate::{fp::Fp, fp4::Fp4};
 pub const DIGEST_WORDS: usize = 8;
 
 /// The result of a SHA-256 hashing function.
-#[derive(Eq, PartialEq, Copy, Zeroable, Pod, Serialize, Deserialize)]
+#[derive(Eq, Hash, Ord, PartialEq, PartialOrd, Copy, Zeroable, Pod, Serialize, Deserialize)]
 #[repr(transparent)]
 pub struct Digest([u32; DIGEST_WORDS]);
 
@@ -203,6 +203,7 @@ pub mod testutil {
         test_fp4s(sha);
 
         crate::sha_rng::testutil::test_sha_rng_impl(sha);
+        crate::merkle::testutil::test_merkle_impl(sha);
     }
 
     fn test_sha_basics<S: Sha>(sha: &S) {
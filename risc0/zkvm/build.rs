@@ -42,6 +42,11 @@ fn main() {
         build.include(tbb_inc_dir);
     }
 
+    // The insecure dev-mode entry points are only built when asked for.
+    if env::var_os("CARGO_FEATURE_DEV_MODE").is_some() {
+        build.define("RISC0_DEV_MODE", None);
+    }
+
     build.compile("risc0-zkvm-sys");
 
     println!("cargo:rustc-link-lib=static=tbb");
@@ -16,12 +16,14 @@
 
 namespace risc0 {
 
+// Must match risc0_zkvm_platform::io
 constexpr size_t kGPIO_SHA = 0x001C0000;
 constexpr size_t kGPIO_Write = 0x001C0004;
 constexpr size_t kGPIO_Commit = 0x001C0008;
 constexpr size_t kGPIO_Fault = 0x001C000C;
 constexpr size_t kGPIO_Log = 0x001C0010;
 constexpr size_t kGPIO_GetKey = 0x001C0014;
+constexpr size_t kGPIO_WriteChannel = 0x001C0018;
 
 struct ShaDescriptor;
 
@@ -30,6 +32,12 @@ struct IoDescriptor {
   uint32_t addr;
 };
 
+struct ChannelDescriptor {
+  uint32_t channel;
+  uint32_t size;
+  uint32_t addr;
+};
+
 struct FaultDescriptor {
   uint32_t addr;
 };
@@ -68,4 +76,8 @@ inline volatile GetKeyDescriptor* volatile* GPIO_GetKey() {
   return reinterpret_cast<volatile GetKeyDescriptor* volatile*>(kGPIO_GetKey);
 }
 
+inline volatile ChannelDescriptor* volatile* GPIO_WriteChannel() {
+  return reinterpret_cast<volatile ChannelDescriptor* volatile*>(kGPIO_WriteChannel);
+}
+
 } // namespace risc0
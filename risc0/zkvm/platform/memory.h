@@ -31,7 +31,7 @@ constexpr size_t k256KB = 0x00040000;
 constexpr size_t k512KB = 0x00080000;
 constexpr size_t k1MB = 0x00100000;
 
-// Must match riscv.ld
+// Must match risc0.ld and risc0_zkvm_platform::memory
 // clang-format off
 MEM_REGION(Stack,  0x00000000, k256KB)
 MEM_REGION(Data,   0x00040000, k256KB)
@@ -45,6 +45,38 @@ MEM_REGION(Output, 0x00340000, k256KB)
 MEM_REGION(Commit, 0x00380000, k256KB)
 // clang-format on
 
+struct MemRegion {
+  uint32_t start;
+  uint32_t len;
+
+  uint32_t end() const { return start + len; }
+};
+
+// The regions a method can size for itself, which the linker script places at the start of the
+// program region. Must match risc0_zkvm_platform::memory::Layout.
+struct MemLayout {
+  uint32_t magic;
+  MemRegion stack;
+  MemRegion data;
+  MemRegion heap;
+  MemRegion input;
+  MemRegion output;
+  MemRegion commit;
+};
+
+// "R0LY" as a little-endian word.
+constexpr uint32_t kLayoutMagic = 0x594C3052;
+
+constexpr MemLayout kDefaultLayout = {
+    kLayoutMagic,
+    {kMemStackStart, kMemStackLen},
+    {kMemDataStart, kMemDataLen},
+    {kMemHeapStart, kMemHeapLen},
+    {kMemInputStart, kMemInputLen},
+    {kMemOutputStart, kMemOutputLen},
+    {kMemCommitStart, kMemCommitLen},
+};
+
 #define PTR_TO(type, name) reinterpret_cast<type*>(kMem##name##Start);
 
 struct ShaDescriptor {
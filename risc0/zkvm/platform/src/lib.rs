@@ -16,3 +16,122 @@
 mod ffi {}
 
 pub const LINKER_SCRIPT: &[u8] = include_bytes!("../risc0.ld");
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkvm_platform::{io, memory};
+
+    const MEMORY_H: &str = include_str!("../memory.h");
+    const IO_H: &str = include_str!("../io.h");
+    const LINKER_SCRIPT: &str = include_str!("../risc0.ld");
+
+    fn parse_size(s: &str) -> usize {
+        let s = s.trim();
+        match s {
+            "k256KB" => 256 * 1024,
+            "k256KB * 2" | "k512KB" => 512 * 1024,
+            "k1MB" => 1024 * 1024,
+            _ if s.ends_with('K') => s[..s.len() - 1].trim().parse::<usize>().unwrap() * 1024,
+            _ if s.ends_with('M') => s[..s.len() - 1].trim().parse::<usize>().unwrap() << 20,
+            _ => usize::from_str_radix(s.trim_start_matches("0x"), 16).unwrap(),
+        }
+    }
+
+    // Returns the (start, len) of each MEM_REGION in memory.h.
+    fn header_region(name: &str) -> (usize, usize) {
+        let prefix = format!("MEM_REGION({},", name);
+        let line = MEMORY_H
+            .lines()
+            .find(|line| line.starts_with(&prefix))
+            .unwrap_or_else(|| panic!("{} not found in memory.h", name));
+        let args: Vec<&str> = line[prefix.len()..line.len() - 1].split(',').collect();
+        (parse_size(args[0]), parse_size(args[1]))
+    }
+
+    // Returns the (origin, length) of each region in the MEMORY block of risc0.ld.
+    fn linker_region(name: &str) -> (usize, usize) {
+        let line = LINKER_SCRIPT
+            .lines()
+            .find(|line| line.trim_start().starts_with(&format!("{} ", name)))
+            .unwrap_or_else(|| panic!("{} not found in risc0.ld", name));
+        let origin = line
+            .split("ORIGIN =")
+            .nth(1)
+            .unwrap()
+            .split(',')
+            .next()
+            .unwrap();
+        let length = line.split("LENGTH =").nth(1).unwrap();
+        (parse_size(origin), parse_size(length))
+    }
+
+    fn gpio(name: &str) -> usize {
+        let prefix = format!("constexpr size_t kGPIO_{} = ", name);
+        let line = IO_H
+            .lines()
+            .find(|line| line.starts_with(&prefix))
+            .unwrap_or_else(|| panic!("kGPIO_{} not found in io.h", name));
+        parse_size(line[prefix.len()..].trim_end_matches(';'))
+    }
+
+    #[test]
+    fn memory_h_matches() {
+        for (name, region) in [
+            ("Stack", memory::STACK),
+            ("Data", memory::DATA),
+            ("Heap", memory::HEAP),
+            ("Input", memory::INPUT),
+            ("GPIO", memory::GPIO),
+            ("Prog", memory::PROG),
+            ("SHA", memory::SHA),
+            ("WOM", memory::WOM),
+            ("Output", memory::OUTPUT),
+            ("Commit", memory::COMMIT),
+        ] {
+            assert_eq!(
+                header_region(name),
+                (region.start(), region.len()),
+                "{}",
+                name
+            );
+        }
+        assert!(MEMORY_H.contains(&format!("kMemBits = {};", memory::MEM_BITS)));
+        let magic = format!("kLayoutMagic = 0x{:08X};", memory::Layout::MAGIC);
+        assert!(MEMORY_H.contains(&magic));
+    }
+
+    #[test]
+    fn linker_script_matches() {
+        for (name, region) in [
+            ("stack", memory::STACK),
+            ("data", memory::DATA),
+            ("heap", memory::HEAP),
+            ("input", memory::INPUT),
+            ("gpio", memory::GPIO),
+            ("prog", memory::PROG),
+            ("sha", memory::SHA),
+            ("output", memory::OUTPUT),
+            ("commit", memory::COMMIT),
+        ] {
+            assert_eq!(
+                linker_region(name),
+                (region.start(), region.len()),
+                "{}",
+                name
+            );
+        }
+        let magic = format!("LONG(0x{:08X});", memory::Layout::MAGIC);
+        assert!(LINKER_SCRIPT.contains(&magic));
+    }
+
+    #[test]
+    fn io_h_matches() {
+        assert_eq!(gpio("SHA"), io::GPIO_SHA);
+        assert_eq!(gpio("Write"), io::GPIO_WRITE);
+        assert_eq!(gpio("Commit"), io::GPIO_COMMIT);
+        assert_eq!(gpio("Fault"), io::GPIO_FAULT);
+        assert_eq!(gpio("Log"), io::GPIO_LOG);
+        assert_eq!(gpio("GetKey"), io::GPIO_GETKEY);
+        assert_eq!(gpio("WriteChannel"), io::GPIO_WRITE_CHANNEL);
+    }
+}
@@ -19,10 +19,64 @@
 #include "risc0/core/util.h"
 #include "risc0/zkvm/circuit/constants.h"
 
+#include <cstring>
+#include <sstream>
+
 namespace risc0 {
 
+namespace {
+
+bool inRange(const MemRegion& region, uint32_t start, uint32_t end) {
+  return region.start % 4 == 0 && region.len % 4 == 0 && region.start >= start &&
+         region.start <= end && region.len <= end - region.start;
+}
+
+// Reads the layout at the start of the program region, which risc0.ld (or the linker script
+// generated by risc0-build) puts there. Methods linked without one use the default layout.
+MemLayout loadLayout(const std::map<uint32_t, uint32_t>& image) {
+  auto it = image.find(kMemProgStart);
+  if (it == image.end() || it->second != kLayoutMagic) {
+    return kDefaultLayout;
+  }
+  uint32_t words[sizeof(MemLayout) / sizeof(uint32_t)];
+  for (size_t i = 0; i < sizeof(MemLayout) / sizeof(uint32_t); i++) {
+    auto word = image.find(kMemProgStart + i * sizeof(uint32_t));
+    if (word == image.end()) {
+      throw std::runtime_error("Truncated memory layout");
+    }
+    words[i] = word->second;
+  }
+  MemLayout layout;
+  memcpy(&layout, words, sizeof(MemLayout));
+  // The stack, data, heap and input must be in order below the GPIOs, and the output and the
+  // journal in order in the write-once memory.
+  if (!inRange(layout.stack, 0, layout.data.start) ||
+      !inRange(layout.data, layout.stack.end(), layout.heap.start) ||
+      !inRange(layout.heap, layout.data.end(), layout.input.start) ||
+      !inRange(layout.input, layout.heap.end(), kMemGPIOStart) ||
+      !inRange(layout.output, kMemWOMStart, layout.commit.start) ||
+      !inRange(layout.commit, layout.output.end(), kMemSize)) {
+    throw std::runtime_error("Invalid memory layout");
+  }
+  LOG(1, "Memory layout: input = " << hex(layout.input.start) << "+" << hex(layout.input.len));
+  return layout;
+}
+
+std::string stackOverflowMessage(uint32_t pc, uint32_t depth, uint32_t size) {
+  std::stringstream ss;
+  ss << "StackOverflow: pc = " << hex(pc) << ", stack depth = " << depth
+     << " bytes, stack size = " << size << " bytes";
+  return ss.str();
+}
+
+} // namespace
+
+StackOverflow::StackOverflow(uint32_t pc, uint32_t depth, uint32_t size)
+    : std::runtime_error(stackOverflowMessage(pc, depth, size)), pc(pc), depth(depth) {}
+
 ExecState::ExecState(const std::string& elfFile) {
   startAddr = loadElf(elfFile, kMemSize, image);
+  layout = loadLayout(image);
 }
 
 void ExecState::run(size_t maxSteps, MemoryHandler& io) {
@@ -42,9 +96,11 @@ void ExecState::init(size_t maxSteps, MemoryHandler& io) {
   }
   this->maxSteps = maxSteps;
   done = false;
+  cycles = 0;
 
   LOG(1, "image.size() = " << image.size());
   LOG(1, "numSteps = " << context.numSteps);
+  context.mem.layout = layout;
   io.onInit(context.mem);
 
   code.resize(kCodeSize * context.numSteps);
@@ -89,6 +145,9 @@ bool ExecState::step() {
   // If it's not, normal execution is complete
   if (!isFinal) {
     done = true;
+    cycles = context.curStep;
+  } else {
+    checkStack();
   }
   return !done;
 }
@@ -126,18 +185,43 @@ uint32_t ExecState::getPC() {
 
 std::vector<uint32_t> ExecState::getRegisters() {
   std::vector<uint32_t> out;
-  size_t offset = 30;
   for (size_t i = 0; i < 32; i++) {
-    uint32_t low =
-        data.data()[(i * 2 + offset) * context.numSteps + context.curStep - 1].asUInt32();
-    uint32_t high =
-        data.data()[(i * 2 + offset + 1) * context.numSteps + context.curStep - 1].asUInt32();
-    REQUIRE(low < 65536 && high < 65536);
-    out.push_back(low | (high << 16));
+    out.push_back(getRegister(i));
   }
   return out;
 }
 
+uint32_t ExecState::getRegister(size_t idx) {
+  size_t offset = 30;
+  uint32_t low =
+      data.data()[(idx * 2 + offset) * context.numSteps + context.curStep - 1].asUInt32();
+  uint32_t high =
+      data.data()[(idx * 2 + offset + 1) * context.numSteps + context.curStep - 1].asUInt32();
+  REQUIRE(low < 65536 && high < 65536);
+  return low | (high << 16);
+}
+
+std::vector<uint32_t> ExecState::getOutputRegs() {
+  std::vector<uint32_t> out;
+  const Fp* globals = context.globals;
+  for (size_t i = 0; i < kOutputRegs; i++) {
+    out.push_back(globals[2 * i].asUInt32() | (globals[2 * i + 1].asUInt32() << 16));
+  }
+  return out;
+}
+
+void ExecState::checkStack() {
+  const MemRegion& stack = layout.stack;
+  uint32_t sp = getRegister(2);
+  // The stack starts out empty at its top, and grows down towards the start of the region. Once
+  // it grows past the start, the stack pointer wraps around to the top of the address space.
+  if (sp < stack.start || sp > stack.end()) {
+    // The instruction that moved the stack pointer can't have been a jump, so it's the one just
+    // before the next PC.
+    throw StackOverflow(getPC() - 4, stack.end() - sp, stack.len);
+  }
+}
+
 void ExecState::expand() {
   if (context.numSteps == maxSteps) {
     throw std::runtime_error("Expand failed: at max steps");
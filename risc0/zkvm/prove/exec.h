@@ -16,11 +16,23 @@
 
 #include "risc0/zkvm/prove/step.h"
 
+#include <stdexcept>
 #include <string>
 #include <vector>
 
 namespace risc0 {
 
+// Thrown when the guest's stack pointer leaves the stack region, which happens when the stack
+// grows past its start and wraps around.
+struct StackOverflow : public std::runtime_error {
+  StackOverflow(uint32_t pc, uint32_t depth, uint32_t size);
+
+  // The address of the instruction that moved the stack pointer.
+  uint32_t pc;
+  // How far the stack pointer is below the top of the stack, in bytes.
+  uint32_t depth;
+};
+
 struct ExecState {
   ExecState(const std::string& elfFile);
   // A helper function to call init, step*, fini
@@ -37,16 +49,26 @@ struct ExecState {
   uint32_t getPC();
   // Get the state of all 32 registers
   std::vector<uint32_t> getRegisters();
+  // Get the state of a single register
+  uint32_t getRegister(size_t idx);
+  // Get the output registers, which hold the result of the guest once it halts
+  std::vector<uint32_t> getOutputRegs();
+
+  // Throw a StackOverflow if the stack pointer is outside the stack region
+  void checkStack();
 
   void expand();
 
   uint32_t startAddr;
   std::map<uint32_t, uint32_t> image;
+  MemLayout layout;
   StepContext context;
   std::vector<Fp> code;
   std::vector<Fp> data;
   size_t maxSteps;
   bool done;
+  // The number of cycles the guest ran for, including loading the image, set once it halts
+  size_t cycles;
 };
 
 } // namespace risc0
@@ -45,6 +45,12 @@ static void processSHA(MemoryState& mem, const ShaDescriptor& desc) {
   }
 }
 
+void IoHandler::onWriteChannel(uint32_t channel, const BufferU8& data) {
+  if (channel == 0) {
+    onWrite(data);
+  }
+}
+
 void IoHandler::onFault(const std::string& msg) {
   throw std::runtime_error(msg);
 }
@@ -78,6 +84,16 @@ void MemoryHandler::onWrite(MemoryState& mem, uint32_t cycle, uint32_t addr, uin
       io->onWrite(buf);
     }
   } break;
+  case kGPIO_WriteChannel: {
+    LOG(1, "MemoryHandler::onWrite> GPIO_WriteChannel");
+    ChannelDescriptor desc;
+    mem.loadRegion(value, &desc, sizeof(desc));
+    if (io) {
+      std::vector<uint8_t> buf(desc.size);
+      mem.loadRegion(desc.addr, buf.data(), desc.size);
+      io->onWriteChannel(desc.channel, buf);
+    }
+  } break;
   case kGPIO_Commit: {
     LOG(1, "MemoryHandler::onWrite> GPIO_Commit");
     IoDescriptor desc;
@@ -68,11 +68,10 @@ void RiscVProveCircuit::execute(WriteIOP& iop) {
   LOG(1, "size = " << size);
 
   // Write final low register state
+  std::vector<uint32_t> outputRegs = exec_.getOutputRegs();
   for (size_t i = 0; i < kOutputRegs; i++) {
-    const Fp* globals = exec_.context.globals;
-    uint32_t regVal = globals[2 * i].asUInt32() | (globals[2 * i + 1].asUInt32() << 16);
-    LOG(2, "x" << i + 1 << " = " << hex(regVal));
-    iop.write(&regVal, 1);
+    LOG(2, "x" << i + 1 << " = " << hex(outputRegs[i]));
+    iop.write(&outputRegs[i], 1);
   }
 
   // Write the po2 for size
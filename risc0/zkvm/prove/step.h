@@ -43,6 +43,8 @@ struct MemoryEvent {
 struct MemoryState {
   std::map<uint32_t, uint32_t> data;
   std::set<MemoryEvent> history;
+  // The layout the method was linked with.
+  MemLayout layout = kDefaultLayout;
 
   void dump(size_t logLevel);
 
@@ -61,6 +63,9 @@ struct MemoryState {
 struct IoHandler {
   virtual void onInit(MemoryState& mem) {}
   virtual void onWrite(const BufferU8& data) {}
+  // Called when the guest writes private output to a numbered channel. Channel 0 is the one
+  // onWrite receives, so by default its data goes there and other channels are dropped.
+  virtual void onWriteChannel(uint32_t channel, const BufferU8& data);
   virtual void onCommit(const BufferU8& data) {}
   virtual void onFault(const std::string& msg);
   virtual KeyStore& getKeyStore() = 0;
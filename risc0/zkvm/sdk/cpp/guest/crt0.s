@@ -22,6 +22,9 @@ _start:
     la sp, __stack_init$;
     la a0, __result
     jal ra, __start
+
+.globl _halt;
+_halt:
     la x10, __result
     lw x1, 0(x10)
     lw x2, 4(x10)
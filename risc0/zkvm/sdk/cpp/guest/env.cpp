@@ -67,6 +67,11 @@ void Env::write(const void* data, size_t size) {
   *GPIO_Write() = &io;
 }
 
+void Env::writeTo(uint32_t channel, const void* data, size_t size) {
+  volatile ChannelDescriptor desc{channel, size, reinterpret_cast<uint32_t>(data)};
+  *GPIO_WriteChannel() = &desc;
+}
+
 void Env::commit(const void* data, size_t size) {
   write(data, size);
   message.update(data, size);
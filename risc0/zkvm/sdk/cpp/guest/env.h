@@ -81,6 +81,30 @@ public:
   /// @param obj The object to serialize and write to the host.
   template <typename T> void write(const T* obj) { write(*obj); }
 
+  /// @brief Writes data to the host on an output channel.
+  ///
+  /// The host keeps each channel's data apart. Channel 0 is the one @c write uses.
+  ///
+  /// @param channel The channel to write to.
+  /// @param data A buffer containing data to send to the host.
+  /// @param size The size of the buffer, in bytes.
+  void writeTo(uint32_t channel, const void* data, size_t size);
+
+  /// @brief Writes data to the host on an output channel.
+  ///
+  /// @tparam T The type of object to write to the host.
+  /// @param channel The channel to write to.
+  /// @param obj The object to serialize and write to the host.
+  template <typename T> void writeTo(uint32_t channel, const T& obj) {
+    uint32_t* start = write_ptr;
+    BufferStreamWriter stream(write_ptr);
+    ArchiveWriter writer(stream);
+    writer.transfer(obj);
+    size_t size = (stream.ptr - start) * sizeof(uint32_t);
+    writeTo(channel, start, size);
+    write_ptr = stream.ptr;
+  }
+
   /// @brief Writes data to the official proof output.
   ///
   /// @param data
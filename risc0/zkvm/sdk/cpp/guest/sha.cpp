@@ -31,6 +31,9 @@ static volatile ShaDescriptor* curDescriptor;
 static DigestPtr compress(const void* data, size_t total) {
   // Now, we need to allocate a new output digest to compute into
   Digest* digest = new Digest;
+  // Descriptors are checked against memory once execution has finished, so they can't be
+  // reused. Leave room for the end marker written by finalizeSHA256.
+  REQUIRE(curDescriptor + 1 < reinterpret_cast<volatile ShaDescriptor*>(kMemSHAEnd));
   // Set up the next descriptor
   curDescriptor->typeAndCount = total / 64;
   curDescriptor->idx = 0;
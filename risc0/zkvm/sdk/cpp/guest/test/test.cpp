@@ -210,6 +210,18 @@ TEST(CoreTests, SHAAccel) {
   receipt.verify(methodId);
 }
 
+TEST(CoreTests, ExitCode) {
+  MethodId methodId = makeMethodId("risc0/zkvm/sdk/rust/methods/test_exit");
+  Prover prover("risc0/zkvm/sdk/rust/methods/test_exit", methodId);
+  prover.writeInput(uint32_t(7));
+  prover.writeInput(std::string("bad input"));
+  Receipt receipt = prover.run();
+  // Only verifyWithExitCode accepts a receipt for a failed execution.
+  EXPECT_THROW(receipt.verify(methodId), std::runtime_error);
+  EXPECT_EQ(receipt.verifyWithExitCode(methodId), 7u);
+  EXPECT_EQ(ReceiptReader(receipt).read<std::string>(), "bad input");
+}
+
 INSTANTIATE_TEST_SUITE_P(All,
                          CoreTests,
                          testing::Values(TestParam{"cpp", "risc0/zkvm/sdk/cpp/guest/test/"},
@@ -14,6 +14,7 @@
 
 #include "risc0/zkvm/sdk/cpp/host/c_api.h"
 
+#include <algorithm>
 #include <memory>
 
 #include "risc0/core/log.h"
@@ -63,6 +64,10 @@ struct risc0_receipt {
   risc0::Receipt receipt;
 };
 
+struct risc0_execution {
+  risc0::ExecutionResult result;
+};
+
 void risc0_init() {
   if (const char* level = std::getenv("RISC0_LOG")) {
     risc0::setLogLevel(std::atoi(level));
@@ -96,6 +101,30 @@ void risc0_prover_add_input(risc0_error* err, risc0_prover* ptr, const uint8_t*
   ffi_wrap_void(err, [&] { ptr->prover->writeInput(buf, len); });
 }
 
+void risc0_prover_add_arg(risc0_error* err, risc0_prover* ptr, const char* arg) {
+  ffi_wrap_void(err, [&] { ptr->prover->addArg(arg); });
+}
+
+void risc0_prover_set_env(risc0_error* err, risc0_prover* ptr, const char* key, const char* value) {
+  ffi_wrap_void(err, [&] { ptr->prover->setEnv(key, value); });
+}
+
+void risc0_prover_add_file(risc0_error* err,
+                           risc0_prover* ptr,
+                           const char* name,
+                           const uint8_t* buf,
+                           size_t len) {
+  ffi_wrap_void(err, [&] { ptr->prover->addFile(name, buf, len); });
+}
+
+void risc0_prover_set_context(risc0_error* err, risc0_prover* ptr, const uint32_t* buf) {
+  ffi_wrap_void(err, [&] {
+    risc0::ShaDigest context;
+    std::copy(buf, buf + 8, context.words);
+    ptr->prover->setContext(context);
+  });
+}
+
 const void* risc0_prover_get_output_buf(risc0_error* err, risc0_prover* ptr) {
   return ffi_wrap<const void*>(err, nullptr, [&] { return ptr->prover->getOutput().data(); });
 }
@@ -104,6 +133,28 @@ size_t risc0_prover_get_output_len(risc0_error* err, const risc0_prover* ptr) {
   return ffi_wrap(err, 0, [&] { return ptr->prover->getOutput().size(); });
 }
 
+const void* risc0_prover_get_channel_buf(risc0_error* err, risc0_prover* ptr, uint32_t channel) {
+  return ffi_wrap<const void*>(err, nullptr, [&] {
+    return ptr->prover->getOutput(channel).data();
+  });
+}
+
+size_t risc0_prover_get_channel_len(risc0_error* err, const risc0_prover* ptr, uint32_t channel) {
+  return ffi_wrap(err, 0, [&] { return ptr->prover->getOutput(channel).size(); });
+}
+
+void risc0_prover_set_output_callback(risc0_error* err,
+                                      risc0_prover* ptr,
+                                      uint32_t channel,
+                                      risc0_output_callback callback,
+                                      void* ctx) {
+  ffi_wrap_void(err, [&] {
+    ptr->prover->setOutputCallback(channel, [callback, ctx](const risc0::BufferU8& buf) {
+      callback(ctx, buf.data(), buf.size());
+    });
+  });
+}
+
 risc0_receipt* risc0_prover_run(risc0_error* err, risc0_prover* ptr) {
   return ffi_wrap<risc0_receipt*>(err, nullptr, [&] {
     risc0::Receipt receipt = ptr->prover->run();
@@ -111,6 +162,22 @@ risc0_receipt* risc0_prover_run(risc0_error* err, risc0_prover* ptr) {
   });
 }
 
+#ifdef RISC0_DEV_MODE
+risc0_receipt* risc0_prover_run_dev_mode(risc0_error* err, risc0_prover* ptr) {
+  return ffi_wrap<risc0_receipt*>(err, nullptr, [&] {
+    risc0::Receipt receipt = ptr->prover->runDevMode();
+    return new risc0_receipt{receipt};
+  });
+}
+#endif
+
+risc0_execution* risc0_prover_execute(risc0_error* err, risc0_prover* ptr) {
+  return ffi_wrap<risc0_execution*>(err, nullptr, [&] {
+    risc0::ExecutionResult result = ptr->prover->execute();
+    return new risc0_execution{result};
+  });
+}
+
 void risc0_receipt_verify(risc0_error* err,
                           const risc0_receipt* ptr,
                           const uint8_t* method_id_buf,
@@ -119,6 +186,26 @@ void risc0_receipt_verify(risc0_error* err,
                 [&] { ptr->receipt.verify(risc0::makeMethodId(method_id_buf, method_id_len)); });
 }
 
+uint32_t risc0_receipt_verify_with_exit_code(risc0_error* err,
+                                             const risc0_receipt* ptr,
+                                             const uint8_t* method_id_buf,
+                                             const size_t method_id_len) {
+  return ffi_wrap<uint32_t>(err, 0, [&] {
+    return ptr->receipt.verifyWithExitCode(risc0::makeMethodId(method_id_buf, method_id_len));
+  });
+}
+
+#ifdef RISC0_DEV_MODE
+uint32_t risc0_receipt_verify_allowing_dev_mode(risc0_error* err,
+                                                const risc0_receipt* ptr,
+                                                const uint8_t* method_id_buf,
+                                                const size_t method_id_len) {
+  return ffi_wrap<uint32_t>(err, 0, [&] {
+    return ptr->receipt.verifyAllowingDevMode(risc0::makeMethodId(method_id_buf, method_id_len));
+  });
+}
+#endif
+
 const uint32_t* risc0_receipt_get_seal_buf(risc0_error* err, const risc0_receipt* ptr) {
   return ffi_wrap<const uint32_t*>(err, nullptr, [&] { return ptr->receipt.seal.data(); });
 }
@@ -135,8 +222,32 @@ size_t risc0_receipt_get_journal_len(risc0_error* err, const risc0_receipt* ptr)
   return ffi_wrap(err, 0, [&] { return ptr->receipt.journal.size(); });
 }
 
+uint32_t risc0_receipt_get_exit_code(risc0_error* err, const risc0_receipt* ptr) {
+  return ffi_wrap(err, 0, [&] { return ptr->receipt.getExitCode(); });
+}
+
 void risc0_receipt_free(risc0_error* err, const risc0_receipt* ptr) {
   ffi_wrap_void(err, [&] { delete ptr; });
 }
 
+const void* risc0_execution_get_journal_buf(risc0_error* err, const risc0_execution* ptr) {
+  return ffi_wrap<const void*>(err, nullptr, [&] { return ptr->result.journal.data(); });
+}
+
+size_t risc0_execution_get_journal_len(risc0_error* err, const risc0_execution* ptr) {
+  return ffi_wrap(err, 0, [&] { return ptr->result.journal.size(); });
+}
+
+uint32_t risc0_execution_get_exit_code(risc0_error* err, const risc0_execution* ptr) {
+  return ffi_wrap(err, 0, [&] { return ptr->result.exitCode; });
+}
+
+size_t risc0_execution_get_cycles(risc0_error* err, const risc0_execution* ptr) {
+  return ffi_wrap(err, 0, [&] { return ptr->result.cycles; });
+}
+
+void risc0_execution_free(risc0_error* err, const risc0_execution* ptr) {
+  ffi_wrap_void(err, [&] { delete ptr; });
+}
+
 } // extern "C"
@@ -33,6 +33,7 @@ extern "C" {
 typedef struct risc0_string risc0_string;
 typedef struct risc0_prover risc0_prover;
 typedef struct risc0_receipt risc0_receipt;
+typedef struct risc0_execution risc0_execution;
 
 //
 // Error
@@ -68,12 +69,44 @@ void risc0_prover_free(risc0_error* err, risc0_prover* ptr);
 
 void risc0_prover_add_input(risc0_error* err, risc0_prover* ptr, const uint8_t* buf, size_t len);
 
-size_t risc0_prover_get_num_outputs(risc0_error* err, risc0_prover* ptr);
+void risc0_prover_add_arg(risc0_error* err, risc0_prover* ptr, const char* arg);
 
-const void* risc0_prover_get_output(risc0_error* err, risc0_prover* ptr, size_t idx, size_t len);
+void risc0_prover_set_env(risc0_error* err, risc0_prover* ptr, const char* key, const char* value);
+
+void risc0_prover_add_file(risc0_error* err,
+                           risc0_prover* ptr,
+                           const char* name,
+                           const uint8_t* buf,
+                           size_t len);
+
+const void* risc0_prover_get_output_buf(risc0_error* err, risc0_prover* ptr);
+
+size_t risc0_prover_get_output_len(risc0_error* err, const risc0_prover* ptr);
+
+const void* risc0_prover_get_channel_buf(risc0_error* err, risc0_prover* ptr, uint32_t channel);
+
+size_t risc0_prover_get_channel_len(risc0_error* err, const risc0_prover* ptr, uint32_t channel);
+
+// Receives each write to an output channel, along with the context it was registered with.
+typedef void (*risc0_output_callback)(void* ctx, const uint8_t* buf, size_t len);
+
+void risc0_prover_set_output_callback(risc0_error* err,
+                                      risc0_prover* ptr,
+                                      uint32_t channel,
+                                      risc0_output_callback callback,
+                                      void* ctx);
+
+// Sets the context from the 8 words at buf.
+void risc0_prover_set_context(risc0_error* err, risc0_prover* ptr, const uint32_t* buf);
 
 risc0_receipt* risc0_prover_run(risc0_error* err, risc0_prover* ptr);
 
+risc0_execution* risc0_prover_execute(risc0_error* err, risc0_prover* ptr);
+
+#ifdef RISC0_DEV_MODE
+risc0_receipt* risc0_prover_run_dev_mode(risc0_error* err, risc0_prover* ptr);
+#endif
+
 //
 // Proof
 //
@@ -83,6 +116,18 @@ void risc0_receipt_verify(risc0_error* err,
                           const uint8_t* method_id_buf,
                           const size_t method_id_len);
 
+uint32_t risc0_receipt_verify_with_exit_code(risc0_error* err,
+                                             const risc0_receipt* ptr,
+                                             const uint8_t* method_id_buf,
+                                             const size_t method_id_len);
+
+#ifdef RISC0_DEV_MODE
+uint32_t risc0_receipt_verify_allowing_dev_mode(risc0_error* err,
+                                                const risc0_receipt* ptr,
+                                                const uint8_t* method_id_buf,
+                                                const size_t method_id_len);
+#endif
+
 const uint32_t* risc0_receipt_get_seal_buf(risc0_error* err, const risc0_receipt* ptr);
 
 size_t risc0_receipt_get_seal_len(risc0_error* err, const risc0_receipt* ptr);
@@ -91,8 +136,24 @@ const void* risc0_receipt_get_journal_buf(risc0_error* err, const risc0_receipt*
 
 size_t risc0_receipt_get_journal_len(risc0_error* err, const risc0_receipt* ptr);
 
+uint32_t risc0_receipt_get_exit_code(risc0_error* err, const risc0_receipt* ptr);
+
 void risc0_receipt_free(risc0_error* err, const risc0_receipt* ptr);
 
+//
+// Execution
+//
+
+const void* risc0_execution_get_journal_buf(risc0_error* err, const risc0_execution* ptr);
+
+size_t risc0_execution_get_journal_len(risc0_error* err, const risc0_execution* ptr);
+
+uint32_t risc0_execution_get_exit_code(risc0_error* err, const risc0_execution* ptr);
+
+size_t risc0_execution_get_cycles(risc0_error* err, const risc0_execution* ptr);
+
+void risc0_execution_free(risc0_error* err, const risc0_execution* ptr);
+
 #ifdef __cplusplus
 } // extern "C"
 #endif // __cplusplus
@@ -18,24 +18,86 @@
 #include "risc0/zkp/core/sha256_cpu.h"
 #include "risc0/zkp/prove/prove.h"
 #include "risc0/zkp/verify/verify.h"
+#include "risc0/zkvm/prove/exec.h"
 #include "risc0/zkvm/prove/method_id.h"
 #include "risc0/zkvm/prove/riscv.h"
 #include "risc0/zkvm/verify/riscv.h"
 
+#include <algorithm>
 #include <fstream>
 #include <sstream>
 
 namespace risc0 {
 
-void Receipt::verify(const MethodId& methodId) const {
-  std::unique_ptr<VerifyCircuit> circuit = getRiscVVerifyCircuit(makeMethodDigest(methodId));
-  risc0::verify(*circuit, seal.data(), seal.size());
-  if (journal.size() != seal[8]) {
+namespace {
+
+// Computes the root of the Merkle tree over the entries of a journal. Missing leaves, which pad
+// the tree to a power of two, are zero.
+ShaDigest merkleJournalRoot(const BufferU8& journal) {
+  if (journal.size() % sizeof(uint32_t)) {
+    throw std::runtime_error("Receipt journal is not a whole number of words");
+  }
+  size_t size = journal.size() / sizeof(uint32_t);
+  std::vector<ShaDigest> leaves;
+  size_t pos = 0;
+  while (pos < size) {
+    uint32_t header;
+    memcpy(&header, journal.data() + pos * sizeof(uint32_t), sizeof(uint32_t));
+    size_t len = 1 + (header & ~kEntrySalted) + ((header & kEntrySalted) ? 8 : 0);
+    if (len > size - pos) {
+      throw std::runtime_error("Receipt journal has a truncated entry");
+    }
+    leaves.push_back(shaHash(journal.data() + pos * sizeof(uint32_t), len * sizeof(uint32_t)));
+    pos += len;
+  }
+  size_t rowSize = 1;
+  while (rowSize < leaves.size()) {
+    rowSize *= 2;
+  }
+  std::vector<ShaDigest> nodes(rowSize * 2, ShaDigest::zero());
+  std::copy(leaves.begin(), leaves.end(), nodes.begin() + rowSize);
+  for (size_t i = rowSize - 1; i >= 1; i--) {
+    nodes[i] = shaHashPair(nodes[2 * i], nodes[2 * i + 1]);
+  }
+  return nodes[1];
+}
+
+} // namespace
+
+// Returns the exit code held in the last output register, which is 0 unless the guest failed.
+static uint32_t exitCodeOf(uint32_t last) {
+  if (last & kExitFailure) {
+    return last & ~(kExitFailure | kMerkleJournal | kJournalContext);
+  }
+  return 0;
+}
+
+// Checks the journal against the output registers at the start of the seal.
+static void verifyJournal(const Receipt& receipt) {
+  const BufferU8& journal = receipt.journal;
+  const BufferU32& seal = receipt.seal;
+  if (receipt.getExitCode()) {
+    // A failed execution always hashes the journal.
+    ShaDigest digest = shaHash(journal.data(), journal.size());
+    if (memcmp(&digest, seal.data(), sizeof(ShaDigest)) != 0) {
+      throw std::runtime_error("Receipt journal/seal root mismatch");
+    }
+    return;
+  }
+  uint32_t size = seal[8] & ~(kMerkleJournal | kJournalContext);
+  if (journal.size() != size) {
     std::stringstream ss;
     ss << "Receipt::verify> journal size (" << journal.size() << ") does not match receipt seal ("
-       << seal[8] << ")";
+       << size << ")";
     throw std::runtime_error(ss.str());
   }
+  if (seal[8] & kMerkleJournal) {
+    ShaDigest root = merkleJournalRoot(journal);
+    if (memcmp(&root, seal.data(), sizeof(ShaDigest)) != 0) {
+      throw std::runtime_error("Receipt journal/seal root mismatch");
+    }
+    return;
+  }
   if (journal.size() > 32) {
     ShaDigest digest = shaHash(journal.data(), journal.size());
     if (memcmp(&digest, seal.data(), sizeof(ShaDigest)) != 0) {
@@ -48,6 +110,44 @@ void Receipt::verify(const MethodId& methodId) const {
   }
 }
 
+void Receipt::verify(const MethodId& methodId) const {
+  uint32_t exitCode = verifyWithExitCode(methodId);
+  if (exitCode) {
+    std::stringstream ss;
+    ss << "Receipt::verify> receipt is for an execution which exited with code " << exitCode;
+    throw std::runtime_error(ss.str());
+  }
+}
+
+uint32_t Receipt::verifyWithExitCode(const MethodId& methodId) const {
+  if (isDevMode()) {
+    throw std::runtime_error("Receipt::verify> dev-mode receipt has a fake seal");
+  }
+  std::unique_ptr<VerifyCircuit> circuit = getRiscVVerifyCircuit(makeMethodDigest(methodId));
+  risc0::verify(*circuit, seal.data(), seal.size());
+  verifyJournal(*this);
+  return getExitCode();
+}
+
+#ifdef RISC0_DEV_MODE
+uint32_t Receipt::verifyAllowingDevMode(const MethodId& methodId) const {
+  if (!isDevMode()) {
+    return verifyWithExitCode(methodId);
+  }
+  LOG(0, "WARNING: accepting a dev-mode receipt, which proves nothing");
+  verifyJournal(*this);
+  return getExitCode();
+}
+#endif
+
+bool Receipt::isDevMode() const {
+  return seal.size() == kOutputRegs + 1 && seal[kOutputRegs] == kDevModeSeal;
+}
+
+uint32_t Receipt::getExitCode() const {
+  return exitCodeOf(seal[8]);
+}
+
 struct Prover::Impl : public IoHandler {
   Impl(const std::string& elfPath, const MethodId& methodId)
       : elfPath(elfPath)
@@ -62,20 +162,92 @@ struct Prover::Impl : public IoHandler {
 
   void onInit(MemoryState& mem) override {
     LOG(1, "Prover::onInit>");
-    uint32_t addr = kMemInputStart;
+    // The input goes where the method was linked to find it.
+    const MemRegion& input = mem.layout.input;
+    uint32_t addr = input.start;
     for (uint32_t word : inputStream.vec) {
-      if (addr > kMemInputEnd) {
+      if (addr >= input.end()) {
         throw std::runtime_error("Out of memory: inputs");
       }
       LOG(1, "  " << hex(addr) << ": " << hex(word));
       mem.store(addr, word);
       addr += sizeof(uint32_t);
     }
+    BufferU32 table = makeStartupTable();
+    if (table.size() * sizeof(uint32_t) > input.end() - addr) {
+      throw std::runtime_error("Out of memory: inputs");
+    }
+    uint32_t tableAddr = input.end() - table.size() * sizeof(uint32_t);
+    for (uint32_t word : table) {
+      mem.store(tableAddr, word);
+      tableAddr += sizeof(uint32_t);
+    }
+  }
+
+  // Lays out the arguments, the environment variables as (key, value) pairs, and the files as
+  // (name, contents) pairs, each as a serialized list, then the context as a serialized option,
+  // followed by the length of the table in words. Without any of these, the table is just a length
+  // of 0.
+  BufferU32 makeStartupTable() {
+    BufferU32 table;
+    if (args.empty() && vars.empty() && files.empty() && !hasContext) {
+      table.push_back(0);
+      return table;
+    }
+    table.push_back(args.size());
+    for (const auto& arg : args) {
+      pushString(table, arg);
+    }
+    table.push_back(vars.size());
+    for (const auto& var : vars) {
+      pushString(table, var.first);
+      pushString(table, var.second);
+    }
+    table.push_back(files.size());
+    for (const auto& file : files) {
+      pushString(table, file.first);
+      pushBytes(table, file.second.data(), file.second.size());
+    }
+    table.push_back(hasContext);
+    if (hasContext) {
+      for (uint32_t word : context.words) {
+        table.push_back(word);
+      }
+    }
+    table.push_back(table.size());
+    return table;
+  }
+
+  static void pushString(BufferU32& table, const std::string& str) {
+    pushBytes(table, reinterpret_cast<const uint8_t*>(str.data()), str.size());
+  }
+
+  static void pushBytes(BufferU32& table, const uint8_t* ptr, size_t size) {
+    table.push_back(size);
+    for (size_t i = 0; i < size; i += sizeof(uint32_t)) {
+      uint32_t word = 0;
+      for (size_t j = 0; j < sizeof(uint32_t) && i + j < size; j++) {
+        word |= ptr[i + j] << (8 * j);
+      }
+      table.push_back(word);
+    }
+  }
+
+  void onWrite(const BufferU8& buf) override { onWriteChannel(0, buf); }
+
+  void onWriteChannel(uint32_t channel, const BufferU8& buf) override {
+    LOG(1, "IoHandler::onWriteChannel> " << channel << ": " << buf.size());
+    auto it = callbacks.find(channel);
+    if (it != callbacks.end()) {
+      it->second(buf);
+      return;
+    }
+    BufferU8& output = getOutput(channel);
+    output.insert(output.end(), buf.begin(), buf.end());
   }
 
-  void onWrite(const BufferU8& buf) override {
-    LOG(1, "IoHandler::onWrite> " << buf.size());
-    outputBuffer.insert(outputBuffer.end(), buf.begin(), buf.end());
+  BufferU8& getOutput(uint32_t channel) {
+    return channel == 0 ? outputBuffer : channelBuffers[channel];
   }
 
   void onCommit(const BufferU8& buf) override {
@@ -85,10 +257,24 @@ struct Prover::Impl : public IoHandler {
 
   KeyStore& getKeyStore() override { return keyStore; }
 
+  // Forgets what the guest wrote during a previous run.
+  void reset() {
+    outputBuffer.clear();
+    channelBuffers.clear();
+    commitBuffer.clear();
+  }
+
   std::string elfPath;
   MethodId methodId;
   KeyStore keyStore;
+  std::vector<std::string> args;
+  std::vector<std::pair<std::string, std::string>> vars;
+  std::vector<std::pair<std::string, BufferU8>> files;
+  bool hasContext = false;
+  ShaDigest context;
   BufferU8 outputBuffer;
+  std::map<uint32_t, BufferU8> channelBuffers;
+  std::map<uint32_t, OutputCallback> callbacks;
   BufferU8 commitBuffer;
   VectorStreamWriter inputStream;
   CheckedStreamReader outputStream;
@@ -143,6 +329,14 @@ const BufferU8& Prover::getOutput() {
   return impl->outputBuffer;
 }
 
+const BufferU8& Prover::getOutput(uint32_t channel) {
+  return impl->getOutput(channel);
+}
+
+void Prover::setOutputCallback(uint32_t channel, OutputCallback callback) {
+  impl->callbacks[channel] = std::move(callback);
+}
+
 const BufferU8& Prover::getCommit() {
   return impl->commitBuffer;
 }
@@ -184,7 +378,41 @@ void Prover::writeInput(const void* ptr, size_t size) {
   }
 }
 
+void Prover::addArg(const std::string& arg) {
+  LOG(1, "Prover::addArg> " << arg);
+  impl->args.push_back(arg);
+}
+
+void Prover::setEnv(const std::string& key, const std::string& value) {
+  LOG(1, "Prover::setEnv> " << key << "=" << value);
+  for (auto& var : impl->vars) {
+    if (var.first == key) {
+      var.second = value;
+      return;
+    }
+  }
+  impl->vars.emplace_back(key, value);
+}
+
+void Prover::addFile(const std::string& name, const void* ptr, size_t size) {
+  LOG(1, "Prover::addFile> " << name << ", size: " << size);
+  for (const auto& file : impl->files) {
+    if (file.first == name) {
+      throw std::runtime_error("Duplicate file: " + name);
+    }
+  }
+  const uint8_t* ptr_u8 = static_cast<const uint8_t*>(ptr);
+  impl->files.emplace_back(name, BufferU8(ptr_u8, ptr_u8 + size));
+}
+
+void Prover::setContext(const ShaDigest& context) {
+  LOG(1, "Prover::setContext>");
+  impl->hasContext = true;
+  impl->context = context;
+}
+
 Receipt Prover::run() {
+  impl->reset();
   // Set the memory handlers to call back to the impl
   MemoryHandler handler(impl.get());
   // Make the circuit
@@ -192,9 +420,38 @@ Receipt Prover::run() {
   BufferU32 seal = prove(*circuit);
   // Attach the full version of the output journal + construct receipt object
   Receipt receipt{getCommit(), seal};
-  // Verify receipt to make sure it works
-  receipt.verify(impl->methodId);
+  // Verify receipt to make sure it works, which it does for a failed execution as well
+  receipt.verifyWithExitCode(impl->methodId);
+  return receipt;
+}
+
+ExecutionResult Prover::execute() {
+  impl->reset();
+  MemoryHandler handler(impl.get());
+  ExecState exec(impl->elfPath);
+  exec.run(kMaxCycles, handler);
+  // The last output register holds the exit code for a failure, as in the seal.
+  uint32_t result = exec.getOutputRegs()[8];
+  uint32_t exitCode = exitCodeOf(result);
+  LOG(1, "Prover::execute> cycles: " << exec.cycles << ", exit code: " << exitCode);
+  return ExecutionResult{getCommit(), exitCode, exec.cycles};
+}
+
+#ifdef RISC0_DEV_MODE
+Receipt Prover::runDevMode() {
+  LOG(0, "WARNING: dev mode is enabled, so receipts are not proven");
+  impl->reset();
+  MemoryHandler handler(impl.get());
+  ExecState exec(impl->elfPath);
+  exec.run(kMaxCycles, handler);
+  // The fake seal holds the output registers, just like a real one, so the journal can still be
+  // checked against it.
+  BufferU32 seal = exec.getOutputRegs();
+  seal.push_back(kDevModeSeal);
+  Receipt receipt{getCommit(), seal};
+  receipt.verifyAllowingDevMode(impl->methodId);
   return receipt;
 }
+#endif
 
 } // namespace risc0
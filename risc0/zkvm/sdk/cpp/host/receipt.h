@@ -21,6 +21,8 @@
 #include "risc0/zkvm/prove/step.h"
 
 #include <array>
+#include <functional>
+#include <map>
 #include <memory>
 #include <vector>
 
@@ -44,13 +46,52 @@ private:
 static_assert(is_stream_reader<CheckedStreamReader>(),
               "CheckedStreamReader must conform to the stream reader model");
 
+// Set in the last output register, along with the exit code, when the guest exits with a
+// failure. The other output registers then hold the digest of the journal.
+constexpr uint32_t kExitFailure = 1u << 31;
+
+// Set in the last output register, along with the journal length or exit code, when the guest
+// commits its journal as a Merkle tree. Unless the guest failed, the other output registers then
+// hold the Merkle root of the journal entries.
+constexpr uint32_t kMerkleJournal = 1u << 30;
+
+// Set in the last output register, along with the journal length or exit code, when the journal
+// starts with the context the host supplied.
+constexpr uint32_t kJournalContext = 1u << 29;
+
+// Set in the header word of a Merkle journal entry which starts with an 8 word salt. The rest of
+// the header is the number of words of data which follow.
+constexpr uint32_t kEntrySalted = 1u << 31;
+
+// Marks the seal of a dev-mode receipt, where a real seal has its po2 after the output registers.
+// Such a seal proves nothing, so only verifiers which explicitly allow dev mode accept it.
+constexpr uint32_t kDevModeSeal = 0x56443052; // "R0DV"
+
 struct Receipt {
   BufferU8 journal;
   BufferU32 seal;
 
-  // Verify a receipt against some code, throws if invalid.
+  // Verify a receipt against some code, throws if invalid or if it is for a failed execution.
+  // Dev-mode receipts are rejected.
   void verify(const MethodId& methodId) const;
 
+  // Verify a receipt against some code, throws if invalid. A receipt for a failed execution is
+  // accepted, and its exit code is returned, which is 0 if the guest halted normally.
+  uint32_t verifyWithExitCode(const MethodId& methodId) const;
+
+#ifdef RISC0_DEV_MODE
+  // INSECURE: verify a receipt as verifyWithExitCode() does, but also accept a dev-mode receipt,
+  // checking only that its journal matches the output registers in its fake seal. Only built with
+  // RISC0_DEV_MODE defined, which the dev-mode cargo feature does.
+  uint32_t verifyAllowingDevMode(const MethodId& methodId) const;
+#endif
+
+  // Returns whether this is a dev-mode receipt, whose seal is fake.
+  bool isDevMode() const;
+
+  // Returns the code the guest exited with, which is 0 unless it exited with a failure.
+  uint32_t getExitCode() const;
+
   template <typename Archive> void transfer(Archive& ar) {
     ar.transfer(journal);
     ar.transfer(seal);
@@ -72,6 +113,18 @@ private:
   ArchiveReader<CheckedStreamReader> archive;
 };
 
+// The result of running a method without proving it.
+struct ExecutionResult {
+  BufferU8 journal;
+  // The code the guest exited with, which is 0 unless it exited with a failure.
+  uint32_t exitCode;
+  // The number of cycles the guest ran for.
+  size_t cycles;
+};
+
+// Receives the private output the guest writes to a channel, one write at a time.
+using OutputCallback = std::function<void(const BufferU8&)>;
+
 class Prover {
 public:
   Prover(const std::string& elfPath, const MethodId& methodId);
@@ -86,8 +139,30 @@ public:
 
   template <typename T> void writeInput(const T& obj) { getInputWriter().transfer(obj); }
 
+  // Add an argument for the guest. Arguments, environment variables and files are placed at the
+  // end of the input region, apart from the input stream.
+  void addArg(const std::string& arg);
+
+  // Set an environment variable for the guest, replacing any previous value.
+  void setEnv(const std::string& key, const std::string& value);
+
+  // Attach a named read-only file, which the guest can open by name.
+  void addFile(const std::string& name, const void* ptr, size_t size);
+
+  // Set a public context, such as a nonce or the digest of a request, which the guest commits to
+  // the journal ahead of anything else, so the receipt can't be replayed for another request.
+  void setContext(const ShaDigest& context);
+
   const BufferU8& getOutput();
 
+  // Returns the private output the guest wrote to the given channel. Channel 0 is the same as
+  // getOutput().
+  const BufferU8& getOutput(uint32_t channel);
+
+  // Send each write to the given channel to the callback as it happens, instead of collecting it
+  // for getOutput.
+  void setOutputCallback(uint32_t channel, OutputCallback callback);
+
   const BufferU8& getCommit();
 
   template <typename T> T readOutput() {
@@ -104,6 +179,17 @@ public:
 
   Receipt run();
 
+  // Run the method without proving it, which is much faster than run but gives no seal. The
+  // output and journal are then available just as after run.
+  ExecutionResult execute();
+
+#ifdef RISC0_DEV_MODE
+  // INSECURE: run the method without proving it, as execute() does, and make a receipt with the
+  // real journal but a fake seal. Only meant for fast tests, so only built with RISC0_DEV_MODE
+  // defined; see Receipt::isDevMode.
+  Receipt runDevMode();
+#endif
+
 private:
   ArchiveWriter<VectorStreamWriter>& getInputWriter();
   ArchiveReader<CheckedStreamReader>& getOutputReader();
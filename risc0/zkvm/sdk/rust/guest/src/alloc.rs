@@ -17,12 +17,15 @@ use core::{
     cell::UnsafeCell,
 };
 
-use crate::{_fault, align_up, REGION_HEAP_END, REGION_HEAP_START, WORD_SIZE};
+use crate::{_fault, align_up, WORD_SIZE};
 
 // Bump pointer allocator for *single* core systems
+//
+// The heap comes from the method's layout, so the allocator starts out empty
+// and finds the heap on the first allocation.
 struct BumpPointerAlloc {
     head: UnsafeCell<usize>,
-    end: usize,
+    end: UnsafeCell<usize>,
 }
 
 // SAFETY: single threaded environment
@@ -32,11 +35,17 @@ unsafe impl Sync for BumpPointerAlloc {}
 unsafe impl GlobalAlloc for BumpPointerAlloc {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
         let head = self.head.get();
+        let end = self.end.get();
+        if *end == 0 {
+            let heap = crate::layout().heap;
+            *head = heap.start();
+            *end = heap.end();
+        }
 
         // move start up to the next alignment boundary
         let alloc_start = align_up(*head, WORD_SIZE);
         let alloc_end = alloc_start.checked_add(layout.size()).unwrap();
-        if alloc_end > self.end {
+        if alloc_end > *end {
             _fault();
         } else {
             *head = alloc_end;
@@ -52,8 +61,8 @@ unsafe impl GlobalAlloc for BumpPointerAlloc {
 #[cfg(target_arch = "riscv32")]
 #[global_allocator]
 static HEAP: BumpPointerAlloc = BumpPointerAlloc {
-    head: UnsafeCell::new(REGION_HEAP_START),
-    end: REGION_HEAP_END,
+    head: UnsafeCell::new(0),
+    end: UnsafeCell::new(0),
 };
 
 #[cfg(target_arch = "riscv32")]
@@ -0,0 +1,594 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The ZKVM is a 32-bit machine, so everything here works a 32-bit word at a
+//! time. A product of two words is computed as a `u64`, which compiles to a
+//! single `mul` and `mulhu` pair, and carries are propagated a word at a time,
+//! so no wider arithmetic needs to be emulated.
+//!
+//! # Example
+//!
+//! ```
+//! use risc0_zkvm_guest::bigint::{Montgomery, U256};
+//!
+//! // The prime of the P-256 curve.
+//! let p = U256::from_be_hex("ffffffff00000001000000000000000000000000ffffffffffffffffffffffff");
+//! let field = Montgomery::new(&p);
+//! let a = U256::from_u32(3);
+//! let b = p.overflowing_sub(&U256::ONE).0;
+//! assert_eq!(field.mul_mod(&a, &b), p.overflowing_sub(&a).0);
+//! ```
+
+use core::cmp::Ordering;
+
+/// An unsigned integer of `N` 32-bit words, least significant word first.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Uint<const N: usize>(pub [u32; N]);
+
+/// A 256-bit unsigned integer.
+pub type U256 = Uint<8>;
+
+/// A 384-bit unsigned integer.
+pub type U384 = Uint<12>;
+
+// Returns a + b * c + carry, as the low word and the carry.
+#[inline(always)]
+fn mac(a: u32, b: u32, c: u32, carry: u32) -> (u32, u32) {
+    let wide = a as u64 + b as u64 * c as u64 + carry as u64;
+    (wide as u32, (wide >> 32) as u32)
+}
+
+// Returns a + b + carry, as the low word and the carry.
+#[inline(always)]
+fn adc(a: u32, b: u32, carry: u32) -> (u32, u32) {
+    let wide = a as u64 + b as u64 + carry as u64;
+    (wide as u32, (wide >> 32) as u32)
+}
+
+// Returns a - b - borrow, as the low word and the borrow, which is 0 or 1.
+#[inline(always)]
+fn sbb(a: u32, b: u32, borrow: u32) -> (u32, u32) {
+    let wide = (a as u64).wrapping_sub(b as u64 + borrow as u64);
+    (wide as u32, (wide >> 63) as u32)
+}
+
+impl<const N: usize> Uint<N> {
+    /// The value 0.
+    pub const ZERO: Self = Uint([0; N]);
+
+    /// The value 1.
+    pub const ONE: Self = {
+        let mut words = [0; N];
+        words[0] = 1;
+        Uint(words)
+    };
+
+    /// The largest value.
+    pub const MAX: Self = Uint([u32::MAX; N]);
+
+    /// Creates an integer from a single word.
+    pub const fn from_u32(value: u32) -> Self {
+        let mut words = [0; N];
+        words[0] = value;
+        Uint(words)
+    }
+
+    /// Creates an integer from big-endian bytes, of which there must be
+    /// exactly `4 * N`.
+    pub fn from_be_slice(bytes: &[u8]) -> Self {
+        assert_eq!(bytes.len(), 4 * N, "Expected {} bytes", 4 * N);
+        let mut words = [0; N];
+        for (word, chunk) in words.iter_mut().rev().zip(bytes.chunks_exact(4)) {
+            *word = u32::from_be_bytes(chunk.try_into().unwrap());
+        }
+        Uint(words)
+    }
+
+    /// Creates an integer from big-endian hex, with exactly `8 * N` digits.
+    pub fn from_be_hex(hex: &str) -> Self {
+        assert_eq!(hex.len(), 8 * N, "Expected {} hex digits", 8 * N);
+        let mut words = [0; N];
+        for (word, chunk) in words.iter_mut().rev().zip(hex.as_bytes().chunks_exact(8)) {
+            let chunk = core::str::from_utf8(chunk).unwrap();
+            *word = u32::from_str_radix(chunk, 16).expect("Invalid hex digit");
+        }
+        Uint(words)
+    }
+
+    /// Writes the integer as big-endian bytes to `bytes`, which must be
+    /// exactly `4 * N` bytes long.
+    pub fn write_be_slice(&self, bytes: &mut [u8]) {
+        assert_eq!(bytes.len(), 4 * N, "Expected {} bytes", 4 * N);
+        for (word, chunk) in self.0.iter().rev().zip(bytes.chunks_exact_mut(4)) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+    }
+
+    /// Creates an integer from little-endian bytes, of which there must be
+    /// exactly `4 * N`.
+    pub fn from_le_slice(bytes: &[u8]) -> Self {
+        assert_eq!(bytes.len(), 4 * N, "Expected {} bytes", 4 * N);
+        let mut words = [0; N];
+        for (word, chunk) in words.iter_mut().zip(bytes.chunks_exact(4)) {
+            *word = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+        Uint(words)
+    }
+
+    /// Writes the integer as little-endian bytes to `bytes`, which must be
+    /// exactly `4 * N` bytes long.
+    pub fn write_le_slice(&self, bytes: &mut [u8]) {
+        assert_eq!(bytes.len(), 4 * N, "Expected {} bytes", 4 * N);
+        for (word, chunk) in self.0.iter().zip(bytes.chunks_exact_mut(4)) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+    }
+
+    /// Returns true if the integer is 0.
+    pub fn is_zero(&self) -> bool {
+        self.0.iter().all(|word| *word == 0)
+    }
+
+    /// Returns the bit at the given position, counting from the least
+    /// significant bit.
+    pub fn bit(&self, idx: usize) -> bool {
+        (self.0[idx / 32] >> (idx % 32)) & 1 == 1
+    }
+
+    /// Returns the number of bits needed to represent the integer.
+    pub fn bits(&self) -> usize {
+        for i in (0..N).rev() {
+            if self.0[i] != 0 {
+                return i * 32 + 32 - self.0[i].leading_zeros() as usize;
+            }
+        }
+        0
+    }
+
+    /// Returns `self >> shift`, for a shift of less than `32 * N` bits.
+    pub fn shr(&self, shift: usize) -> Self {
+        let (words, bits) = (shift / 32, shift % 32);
+        let mut out = [0; N];
+        for i in 0..N - words {
+            out[i] = self.0[i + words] >> bits;
+            if bits != 0 && i + words + 1 < N {
+                out[i] |= self.0[i + words + 1] << (32 - bits);
+            }
+        }
+        Uint(out)
+    }
+
+    /// Returns `self + rhs`, and whether the addition overflowed.
+    pub fn overflowing_add(&self, rhs: &Self) -> (Self, bool) {
+        let mut out = [0; N];
+        let mut carry = 0;
+        for i in 0..N {
+            (out[i], carry) = adc(self.0[i], rhs.0[i], carry);
+        }
+        (Uint(out), carry != 0)
+    }
+
+    /// Returns `self - rhs`, and whether the subtraction underflowed.
+    pub fn overflowing_sub(&self, rhs: &Self) -> (Self, bool) {
+        let mut out = [0; N];
+        let mut borrow = 0;
+        for i in 0..N {
+            (out[i], borrow) = sbb(self.0[i], rhs.0[i], borrow);
+        }
+        (Uint(out), borrow != 0)
+    }
+
+    /// Returns the full product `self * rhs`, as the low and high halves.
+    pub fn widening_mul(&self, rhs: &Self) -> (Self, Self) {
+        let mut lo = [0; N];
+        let mut hi = [0; N];
+        for i in 0..N {
+            let mut carry = 0;
+            for j in 0..N {
+                let k = i + j;
+                let word = if k < N { &mut lo[k] } else { &mut hi[k - N] };
+                (*word, carry) = mac(*word, self.0[i], rhs.0[j], carry);
+            }
+            hi[i] = carry;
+        }
+        (Uint(lo), Uint(hi))
+    }
+}
+
+impl<const N: usize> Default for Uint<N> {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl<const N: usize> Ord for Uint<N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.iter().rev().cmp(other.0.iter().rev())
+    }
+}
+
+impl<const N: usize> PartialOrd for Uint<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Arithmetic modulo an odd modulus, using Montgomery multiplication.
+///
+/// Values in Montgomery form are `x * R mod m`, where `R = 2^(32 * N)`.
+/// [Montgomery::mul] multiplies two such values without any division, so a
+/// long computation converts its inputs with [Montgomery::to_montgomery],
+/// works in Montgomery form throughout, and converts back at the end with
+/// [Montgomery::from_montgomery]. Addition and subtraction work the same in
+/// either form.
+#[derive(Clone, Debug)]
+pub struct Montgomery<const N: usize> {
+    modulus: Uint<N>,
+    // -modulus^-1 mod 2^32.
+    m_inv: u32,
+    // R^2 mod modulus.
+    r2: Uint<N>,
+}
+
+impl<const N: usize> Montgomery<N> {
+    /// Prepares arithmetic modulo the given odd modulus.
+    pub fn new(modulus: &Uint<N>) -> Self {
+        assert!(modulus.0[0] & 1 == 1, "Modulus must be odd");
+        assert!(*modulus > Uint::ONE, "Modulus must be greater than 1");
+
+        // Newton's method doubles the number of correct low bits of the
+        // inverse each step, and any odd number is its own inverse mod 8.
+        let m0 = modulus.0[0];
+        let mut inv = m0;
+        for _ in 0..4 {
+            inv = inv.wrapping_mul(2u32.wrapping_sub(m0.wrapping_mul(inv)));
+        }
+
+        // Start from 2^k mod m for the top bit of m, and keep doubling up to
+        // 2^N * R, which is 2^N in Montgomery form.
+        let top = modulus.bits() - 1;
+        let mut x = Uint::ZERO;
+        x.0[top / 32] = 1 << (top % 32);
+        for _ in top..33 * N {
+            let (doubled, carry) = x.overflowing_add(&x);
+            x = if carry || doubled >= *modulus {
+                doubled.overflowing_sub(modulus).0
+            } else {
+                doubled
+            };
+        }
+
+        // Squaring 2^N five times in Montgomery form gives 2^(32 * N) = R,
+        // in Montgomery form, which is R^2. That takes a few multiplications
+        // rather than the hundreds of doublings it would take to get there.
+        let mut field = Montgomery {
+            modulus: *modulus,
+            m_inv: inv.wrapping_neg(),
+            r2: Uint::ZERO,
+        };
+        for _ in 0..5 {
+            x = field.mul(&x, &x);
+        }
+        field.r2 = x;
+        field
+    }
+
+    /// Returns the modulus.
+    pub fn modulus(&self) -> &Uint<N> {
+        &self.modulus
+    }
+
+    /// Returns 1 in Montgomery form.
+    pub fn one(&self) -> Uint<N> {
+        self.to_montgomery(&Uint::ONE)
+    }
+
+    /// Converts a value less than the modulus to Montgomery form.
+    pub fn to_montgomery(&self, a: &Uint<N>) -> Uint<N> {
+        assert!(*a < self.modulus, "Value must be less than the modulus");
+        self.mul(a, &self.r2)
+    }
+
+    /// Converts a value from Montgomery form.
+    pub fn from_montgomery(&self, a: &Uint<N>) -> Uint<N> {
+        self.mul(a, &Uint::ONE)
+    }
+
+    /// Returns `a * b * R^-1 mod m`, which is the product of two values in
+    /// Montgomery form, in Montgomery form.
+    ///
+    /// This interleaves the multiplication with the reduction a word at a
+    /// time (the CIOS method), so it needs only `2 * N^2 + N` word products.
+    pub fn mul(&self, a: &Uint<N>, b: &Uint<N>) -> Uint<N> {
+        let m = &self.modulus.0;
+        // The running total has two more words than the modulus.
+        let mut t = [0; N];
+        let mut t_hi = 0;
+        for i in 0..N {
+            let mut carry = 0;
+            for j in 0..N {
+                (t[j], carry) = mac(t[j], a.0[j], b.0[i], carry);
+            }
+            let (sum, t_top) = adc(t_hi, carry, 0);
+
+            // Add a multiple of m which clears the low word, and shift down
+            // a word.
+            let q = t[0].wrapping_mul(self.m_inv);
+            let (_, mut carry) = mac(t[0], q, m[0], 0);
+            for j in 1..N {
+                (t[j - 1], carry) = mac(t[j], q, m[j], carry);
+            }
+            (t[N - 1], carry) = adc(sum, carry, 0);
+            t_hi = t_top + carry;
+        }
+        let t = Uint(t);
+        if t_hi != 0 || t >= self.modulus {
+            t.overflowing_sub(&self.modulus).0
+        } else {
+            t
+        }
+    }
+
+    /// Returns `a mod m`, for any `a`.
+    pub fn reduce(&self, a: &Uint<N>) -> Uint<N> {
+        self.mul(&self.mul(a, &Uint::ONE), &self.r2)
+    }
+
+    /// Returns `(hi * 2^(32 * N) + lo) mod m`, for a double-width value
+    /// such as a product or a wide hash.
+    pub fn reduce_wide(&self, lo: &Uint<N>, hi: &Uint<N>) -> Uint<N> {
+        self.add(&self.mul(hi, &self.r2), &self.reduce(lo))
+    }
+
+    /// Returns `a * b mod m`, for values which aren't in Montgomery form.
+    ///
+    /// Each call costs two Montgomery multiplications, so it is better to
+    /// convert to Montgomery form for more than a couple of products.
+    pub fn mul_mod(&self, a: &Uint<N>, b: &Uint<N>) -> Uint<N> {
+        self.mul(&self.mul(a, b), &self.r2)
+    }
+
+    /// Returns `a + b mod m`.
+    pub fn add(&self, a: &Uint<N>, b: &Uint<N>) -> Uint<N> {
+        let (sum, carry) = a.overflowing_add(b);
+        if carry || sum >= self.modulus {
+            sum.overflowing_sub(&self.modulus).0
+        } else {
+            sum
+        }
+    }
+
+    /// Returns `a - b mod m`.
+    pub fn sub(&self, a: &Uint<N>, b: &Uint<N>) -> Uint<N> {
+        let (diff, borrow) = a.overflowing_sub(b);
+        if borrow {
+            diff.overflowing_add(&self.modulus).0
+        } else {
+            diff
+        }
+    }
+
+    /// Returns `base^exp`, for a base in Montgomery form, in Montgomery form.
+    pub fn pow<const M: usize>(&self, base: &Uint<N>, exp: &Uint<M>) -> Uint<N> {
+        let mut acc = self.one();
+        for i in (0..exp.bits()).rev() {
+            acc = self.mul(&acc, &acc);
+            if exp.bit(i) {
+                acc = self.mul(&acc, base);
+            }
+        }
+        acc
+    }
+
+    /// Returns `a^-1`, for a value in Montgomery form which is coprime to the
+    /// modulus, such as any nonzero value when the modulus is prime, in
+    /// Montgomery form.
+    ///
+    /// This uses the binary extended Euclidean algorithm, which costs about
+    /// as much as a few dozen multiplications, rather than the hundreds an
+    /// exponentiation by Fermat's little theorem takes.
+    pub fn invert(&self, a: &Uint<N>) -> Uint<N> {
+        assert!(!a.is_zero(), "Zero has no inverse");
+        // Keeps x1 * a = u and x2 * a = v, halving u and v while they are
+        // even and subtracting the smaller from the larger, until one is 1.
+        let (mut u, mut v) = (*a, self.modulus);
+        let (mut x1, mut x2) = (Uint::ONE, Uint::ZERO);
+        while u != Uint::ONE && v != Uint::ONE {
+            // u reaches 0 when it equals v, which is then their gcd.
+            assert!(!u.is_zero(), "Value has no inverse");
+            while u.0[0] & 1 == 0 {
+                u = u.shr(1);
+                x1 = self.half(&x1);
+            }
+            while v.0[0] & 1 == 0 {
+                v = v.shr(1);
+                x2 = self.half(&x2);
+            }
+            if u >= v {
+                u = u.overflowing_sub(&v).0;
+                x1 = self.sub(&x1, &x2);
+            } else {
+                v = v.overflowing_sub(&u).0;
+                x2 = self.sub(&x2, &x1);
+            }
+        }
+        // This is the inverse of a * R, which is a^-1 * R^-1, so it needs
+        // two more factors of R.
+        let inv = if u == Uint::ONE { x1 } else { x2 };
+        self.mul(&self.mul(&inv, &self.r2), &self.r2)
+    }
+
+    // Returns `x / 2 mod m`.
+    fn half(&self, x: &Uint<N>) -> Uint<N> {
+        if x.0[0] & 1 == 0 {
+            return x.shr(1);
+        }
+        let (sum, carry) = x.overflowing_add(&self.modulus);
+        let mut half = sum.shr(1);
+        half.0[N - 1] |= (carry as u32) << 31;
+        half
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Montgomery, Uint, U256, U384};
+
+    const P256: &str = "ffffffff00000001000000000000000000000000ffffffffffffffffffffffff";
+    const P384: &str = "fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe\
+                        ffffffff0000000000000000ffffffff";
+
+    fn to_u64(x: &Uint<2>) -> u64 {
+        x.0[0] as u64 | (x.0[1] as u64) << 32
+    }
+
+    fn from_u64(x: u64) -> Uint<2> {
+        Uint([x as u32, (x >> 32) as u32])
+    }
+
+    #[test]
+    fn arithmetic() {
+        let values = [
+            0,
+            1,
+            2,
+            0xffff_ffff,
+            0x1_0000_0000,
+            0x1234_5678_9abc_def0,
+            u64::MAX,
+        ];
+        for a in values {
+            for b in values {
+                let (sum, carry) = from_u64(a).overflowing_add(&from_u64(b));
+                assert_eq!((to_u64(&sum), carry), a.overflowing_add(b));
+                let (diff, borrow) = from_u64(a).overflowing_sub(&from_u64(b));
+                assert_eq!((to_u64(&diff), borrow), a.overflowing_sub(b));
+                let (lo, hi) = from_u64(a).widening_mul(&from_u64(b));
+                let product = a as u128 * b as u128;
+                assert_eq!(
+                    (to_u64(&lo), to_u64(&hi)),
+                    (product as u64, (product >> 64) as u64)
+                );
+                assert_eq!(from_u64(a).cmp(&from_u64(b)), a.cmp(&b));
+            }
+        }
+    }
+
+    #[test]
+    fn modular() {
+        let moduli = [3, 0xffff_fffb, 0xffff_ffff_ffff_ffc5, 0x8000_0000_0000_0001];
+        let values = [0, 1, 2, 0xffff_fffa, 0x1234_5678_9abc_def0, u64::MAX - 1];
+        for m in moduli {
+            let field = Montgomery::new(&from_u64(m));
+            for a in values.map(|a| a % m) {
+                for b in values.map(|b| b % m) {
+                    let expected = (a as u128 * b as u128 % m as u128) as u64;
+                    let (a, b) = (from_u64(a), from_u64(b));
+                    assert_eq!(to_u64(&field.mul_mod(&a, &b)), expected);
+                    let product = field.mul(&field.to_montgomery(&a), &field.to_montgomery(&b));
+                    assert_eq!(to_u64(&field.from_montgomery(&product)), expected);
+                    let sum = (a.0[0] as u128 | (a.0[1] as u128) << 32)
+                        + (b.0[0] as u128 | (b.0[1] as u128) << 32);
+                    assert_eq!(to_u64(&field.add(&a, &b)), (sum % m as u128) as u64);
+                    assert_eq!(field.add(&field.sub(&a, &b), &b), a);
+                }
+            }
+        }
+    }
+
+    // Fermat's little theorem: a^(p-1) = 1 mod p for a prime p.
+    fn check_fermat<const N: usize>(p: &Uint<N>) {
+        let field = Montgomery::new(p);
+        let exp = p.overflowing_sub(&Uint::ONE).0;
+        for a in [2, 3, 0xdead_beef] {
+            let a = field.to_montgomery(&Uint::from_u32(a));
+            assert_eq!(field.from_montgomery(&field.pow(&a, &exp)), Uint::ONE);
+        }
+        assert_eq!(field.mul_mod(&exp, &exp), Uint::ONE);
+        let a = field.to_montgomery(&Uint::from_u32(7));
+        assert_eq!(field.mul(&a, &field.invert(&a)), field.one());
+    }
+
+    #[test]
+    fn fermat() {
+        check_fermat(&U256::from_be_hex(P256));
+        check_fermat(&U384::from_be_hex(P384));
+    }
+
+    #[test]
+    fn invert() {
+        let p = U256::from_be_hex(P256);
+        let field = Montgomery::new(&p);
+        let exp = p.overflowing_sub(&Uint::from_u32(2)).0;
+        for a in [
+            U256::ONE,
+            Uint::from_u32(2),
+            p.shr(1),
+            p.overflowing_sub(&U256::ONE).0,
+        ] {
+            let a = field.to_montgomery(&a);
+            assert_eq!(field.invert(&a), field.pow(&a, &exp));
+        }
+
+        // Any value coprime to the modulus has an inverse.
+        // 2^64 - 1 = 3 * 5 * 17 * 257 * 641 * 65537 * 6700417.
+        let m = u64::MAX;
+        let field = Montgomery::new(&from_u64(m));
+        for a in [1, 2, 7, m - 1] {
+            let a = field.to_montgomery(&from_u64(a));
+            assert_eq!(field.mul(&a, &field.invert(&a)), field.one());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Value has no inverse")]
+    fn invert_not_coprime() {
+        let field = Montgomery::new(&from_u64(15));
+        field.invert(&field.to_montgomery(&from_u64(6)));
+    }
+
+    #[test]
+    fn bytes() {
+        let p = U256::from_be_hex(P256);
+        let mut bytes = [0; 32];
+        p.write_be_slice(&mut bytes);
+        assert_eq!(bytes[0..5], [0xff, 0xff, 0xff, 0xff, 0x00]);
+        assert_eq!(U256::from_be_slice(&bytes), p);
+        assert_eq!(p.bits(), 256);
+        assert_eq!(U256::ONE.bits(), 1);
+        assert!(p.bit(0) && !p.bit(100));
+        p.write_le_slice(&mut bytes);
+        assert_eq!(U256::from_le_slice(&bytes), p);
+        assert_eq!(p.shr(64).0, [0xffff_ffff, 0, 0, 0, 1, 0xffff_ffff, 0, 0]);
+        assert_eq!(p.shr(1).0[0], 0xffff_ffff);
+        assert_eq!(p.shr(1).0[7], 0x7fff_ffff);
+    }
+
+    #[test]
+    fn reduce() {
+        let m = 0xffff_ffff_ffff_ffc5;
+        let field = Montgomery::new(&from_u64(m));
+        for a in [0, 1, m - 1, m, m + 1, u64::MAX] {
+            assert_eq!(to_u64(&field.reduce(&from_u64(a))), a % m);
+            for b in [0, 1, u64::MAX] {
+                let wide = (b as u128) << 64 | a as u128;
+                let expected = (wide % m as u128) as u64;
+                assert_eq!(
+                    to_u64(&field.reduce_wide(&from_u64(a), &from_u64(b))),
+                    expected
+                );
+            }
+        }
+    }
+}
@@ -12,25 +12,93 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::{cell::UnsafeCell, mem::MaybeUninit, slice};
+use _alloc::vec::Vec;
+use core::{
+    cell::UnsafeCell,
+    mem::{self, MaybeUninit},
+    ptr, slice,
+};
 
-use risc0_zkvm_core::Digest;
-use risc0_zkvm_serde::{Deserializer, Serializer, Slice};
-use serde::{Deserialize, Serialize};
+use bytemuck::Pod;
+use risc0_zkp_core::merkle::MerkleTree;
+use risc0_zkvm_core::{Digest, DIGEST_WORDS};
+use risc0_zkvm_serde::{
+    from_slice, serialized_len, AllocVec, Deserializer, Error, Result, Serializer, Slice,
+    StreamWriter,
+};
+use serde::{ser::SerializeTuple, Deserialize, Serialize, Serializer as _};
 
 use crate::{
-    gpio::{IoDescriptor, GPIO_COMMIT, GPIO_DESC_IO, GPIO_WRITE},
-    sha, REGION_COMMIT_LEN, REGION_COMMIT_START, REGION_INPUT_LEN, REGION_INPUT_START,
-    REGION_OUTPUT_LEN, REGION_OUTPUT_START, WORD_SIZE,
+    align_up,
+    gpio::{
+        ChannelDescriptor, IoDescriptor, GPIO_COMMIT, GPIO_DESC_CHANNEL, GPIO_DESC_IO, GPIO_WRITE,
+        GPIO_WRITE_CHANNEL,
+    },
+    sha, REGION_COMMIT_LEN, WORD_SIZE,
 };
 
-struct Env {
+#[cfg(not(target_arch = "riscv32"))]
+use crate::native::{Env, ENV};
+
+#[cfg(target_arch = "riscv32")]
+pub(crate) struct Env {
     input: Deserializer<'static>,
     output: Serializer<Slice<'static>>,
+    output_len: usize,
     commit: Serializer<Slice<'static>>,
     commit_len: usize,
+    commit_capacity: usize,
+    overflow: Option<Overflow>,
+    startup: Option<Startup>,
+    commit_file_digests: bool,
+    context_committed: bool,
+    leaves: Option<Vec<Digest>>,
+}
+
+// A named file attached by the host, as a name and its contents.
+pub(crate) type File = (&'static str, &'static [u8]);
+
+// What the host starts the guest with, besides its input stream.
+#[derive(Default)]
+pub(crate) struct Startup {
+    pub(crate) args: Vec<&'static str>,
+    pub(crate) vars: Vec<(&'static str, &'static str)>,
+    pub(crate) files: Vec<File>,
+    pub(crate) context: Option<Digest>,
+}
+
+// Decodes the startup table at the end of the given input region.
+//
+// The host places the table in the last words of the input region, followed
+// by its length in words, so the input stream read by `read` is unaffected.
+// The table holds the arguments, environment variables, files and context as
+// a serialized `(Vec<&str>, Vec<(&str, &str)>, Vec<(&str, &[u8])>,
+// Option<Digest>)`. The host always writes the table, which is just a 0
+// length when empty.
+pub(crate) fn decode_startup(region: &'static [u32]) -> Startup {
+    let (len, rest) = region.split_last().expect("Empty input region");
+    let len = *len as usize;
+    if len == 0 {
+        return Startup::default();
+    }
+    assert!(len <= rest.len(), "Bad startup table length: {}", len);
+    let (args, vars, files, context) =
+        from_slice(&rest[rest.len() - len..]).expect("Bad startup table");
+    Startup {
+        args,
+        vars,
+        files,
+        context,
+    }
 }
 
+// Words at the end of the commit region reserved for the SHA end marker
+// and length trailer added by finalize.
+const COMMIT_TRAILER_WORDS: usize = 3;
+
+// Number of words of journal which fit in the commit region.
+pub(crate) const COMMIT_CAPACITY: usize = REGION_COMMIT_LEN / WORD_SIZE - COMMIT_TRAILER_WORDS;
+
 struct Once<T> {
     data: UnsafeCell<MaybeUninit<T>>,
 }
@@ -59,127 +127,814 @@ impl<T> Once<T> {
     }
 }
 
+#[cfg(target_arch = "riscv32")]
 static ENV: Once<Env> = Once::new();
 
+#[cfg(target_arch = "riscv32")]
 pub(crate) fn init() {
     ENV.init(Env::new());
 }
 
+#[cfg(target_arch = "riscv32")]
 pub(crate) fn finalize(result: *mut usize) {
-    ENV.get().finalize(result);
+    let env = ENV.get();
+    commit_context(env);
+    env.finalize(result, 0);
+}
+
+// Set in the last output register, along with the exit code, when the guest
+// exits with a failure.
+const EXIT_FAILURE: u32 = 1 << 31;
+
+// Set in the last output register, along with the journal length or exit
+// code, when the journal is committed as a Merkle tree. Unless the guest
+// failed, the other output registers then hold the Merkle root of the journal
+// entries.
+const MERKLE_JOURNAL: u32 = 1 << 30;
+
+// Set in the last output register, along with the journal length or exit
+// code, when the journal starts with the context the host supplied.
+const JOURNAL_CONTEXT: u32 = 1 << 29;
+
+// Set in the header of a journal entry which starts with a salt.
+const ENTRY_SALTED: u32 = 1 << 31;
+
+/// How the receipt commits to the journal.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum JournalMode {
+    /// The receipt holds the SHA-256 digest of the whole journal, or the
+    /// journal itself if it is at most 32 bytes. This is the default.
+    Whole,
+
+    /// Each commit is an entry of the journal, and the receipt holds the root
+    /// of a Merkle tree over the entries. A verifier can then be shown some of
+    /// the entries, with proofs of their inclusion, and not the others.
+    ///
+    /// Each entry is a header word holding the number of words of data, with
+    /// bit 31 set if the entry is salted, followed by the 8 words of salt for
+    /// a salted entry, and then the serialized data. Each leaf is the SHA-256
+    /// digest of an entry. An execution which exits with a failure still
+    /// hashes the whole journal.
+    Merkle,
+}
+
+/// Halt the guest with the given exit code.
+///
+/// An exit code of 0 halts just like returning from `main`. Any other code
+/// halts with a failure: the receipt then records the exit code and the
+/// digest of the journal committed so far, so the guest should commit a
+/// description of the failure before calling this. Exit codes must be less
+/// than 2^29.
+pub fn exit(code: u32) -> ! {
+    assert!(code < JOURNAL_CONTEXT, "Exit code out of range: {}", code);
+    let env = ENV.get();
+    commit_context(env);
+    env.exit(code)
 }
 
 /// Read private data from the host.
+///
+/// Panics if the input can't be deserialized; see [try_read].
 pub fn read<T: Deserialize<'static>>() -> T {
-    ENV.get().read()
+    try_read().unwrap()
+}
+
+/// Read private data from the host, returning an error if the input can't be
+/// deserialized as `T`.
+///
+/// On error, no input is consumed.
+pub fn try_read<T: Deserialize<'static>>() -> Result<T> {
+    ENV.get().try_read_words().map(|(value, _)| value)
+}
+
+/// Read private data from the host, and check that the SHA-256 digest of its
+/// serialized words matches `expected`.
+///
+/// The digest is the same as [sha::digest] gives for the value, so a large
+/// input can be checked against a digest that is known in advance, such as
+/// one committed by another method. Panics, faulting the execution, if the
+/// input can't be deserialized or its digest doesn't match.
+pub fn read_verified<T: Deserialize<'static>>(expected: &Digest) -> T {
+    let (value, digest) = read_with_digest();
+    assert!(
+        digest == *expected,
+        "Input digest mismatch: expected {}, got {}",
+        expected,
+        digest
+    );
+    value
+}
+
+/// Read private data from the host, and commit the SHA-256 digest of its
+/// serialized words to the journal, so the verifier learns which input was
+/// used.
+///
+/// The digest is the same as [sha::digest] gives for the value. Panics if the
+/// input can't be deserialized.
+pub fn read_and_commit_digest<T: Deserialize<'static>>() -> T {
+    let (value, digest) = read_with_digest();
+    commit(&digest);
+    value
+}
+
+// Reads a value, and hashes the words it was deserialized from. The words are
+// copied to the heap for hashing, like any input to `sha::digest_u8_slice`.
+fn read_with_digest<T: Deserialize<'static>>() -> (T, Digest) {
+    let (value, words) = ENV.get().try_read_words().unwrap();
+    (value, sha::digest_u8_slice(bytemuck::cast_slice(words)))
+}
+
+/// Read a plain-old-data value from the host, borrowing it from the input
+/// region instead of deserializing it.
+///
+/// The host provides the bytes of the value as they are, padded with zeros to
+/// a whole number of words. Panics if there isn't enough input left, or if
+/// the value isn't aligned as `T` needs, which can only happen when `T` needs
+/// more than word alignment.
+pub fn read_pod<T: Pod>() -> &'static T {
+    let size = mem::size_of::<T>();
+    let words = ENV
+        .get()
+        .take_words(align_up(size, WORD_SIZE) / WORD_SIZE)
+        .expect("Not enough input for read_pod");
+    let bytes: &[u8] = bytemuck::cast_slice(words);
+    bytemuck::from_bytes(&bytes[..size])
+}
+
+/// Read a slice of plain-old-data values from the host, borrowing it from
+/// the input region instead of deserializing each value.
+///
+/// The host provides the number of values as a word, followed by their bytes
+/// as they are, padded with zeros to a whole number of words. For `u32`, this
+/// is how a `Vec<u32>` is serialized, but not for `u8`: serde writes a word
+/// for each byte of a `Vec<u8>`, while here they are packed four to a word.
+/// Panics if there isn't enough input left, or if the values aren't aligned
+/// as `T` needs, which can only happen when `T` needs more than word
+/// alignment.
+pub fn read_slice<T: Pod>() -> &'static [T] {
+    let len: u32 = read();
+    // A size that overflows, even when rounded up to words, is more than the
+    // input could hold.
+    let size = (len as usize)
+        .checked_mul(mem::size_of::<T>())
+        .filter(|size| size.checked_add(WORD_SIZE - 1).is_some())
+        .expect("Not enough input for read_slice");
+    let words = ENV
+        .get()
+        .take_words(align_up(size, WORD_SIZE) / WORD_SIZE)
+        .expect("Not enough input for read_slice");
+    let bytes: &[u8] = bytemuck::cast_slice(words);
+    bytemuck::cast_slice(&bytes[..size])
 }
 
 /// Write private data to the host.
+///
+/// Panics if the data can't be serialized; see [try_write].
 pub fn write<T: Serialize>(data: &T) {
-    ENV.get().write(data);
+    try_write(data).unwrap();
+}
+
+/// Write private data to the host, returning an error if it can't be
+/// serialized or doesn't fit in the remaining output space.
+///
+/// On error, nothing is written.
+pub fn try_write<T: Serialize>(data: &T) -> Result<()> {
+    ENV.get().try_write_to(0, data)
+}
+
+/// Write private data to the host on the given output channel.
+///
+/// The host keeps each channel's data apart, so a guest can send several
+/// independent streams without framing them itself. Channel 0 is the one
+/// [write] uses.
+///
+/// Panics if the data can't be serialized; see [try_write_to].
+pub fn write_to<T: Serialize>(channel: u32, data: &T) {
+    try_write_to(channel, data).unwrap();
+}
+
+/// Write private data to the host on the given output channel, returning an
+/// error if it can't be serialized or doesn't fit in the remaining output
+/// space, which all channels share.
+///
+/// On error, nothing is written.
+pub fn try_write_to<T: Serialize>(channel: u32, data: &T) -> Result<()> {
+    ENV.get().try_write_to(channel, data)
+}
+
+/// Write a slice of plain-old-data values to the host, copying their bytes
+/// instead of serializing each value.
+///
+/// The host receives the number of values as a word, followed by their bytes
+/// padded with zeros to a whole number of words, as [read_slice] expects.
+/// For `u32`, this is the same as writing a `Vec<u32>`. Panics if the data
+/// doesn't fit in the remaining output space.
+pub fn write_slice<T: Pod>(data: &[T]) {
+    ENV.get().try_write_to(0, &PodSlice::new(data)).unwrap();
 }
 
 /// Commit public data to the journal.
+///
+/// Panics if the data can't be serialized; see [try_commit].
 pub fn commit<T: Serialize>(data: &T) {
-    ENV.get().commit(data);
+    try_commit(data).unwrap();
+}
+
+/// Commit public data to the journal, returning an error if it can't be
+/// serialized.
+///
+/// On error, nothing is committed, so the guest can still commit another
+/// value, such as an error result.
+pub fn try_commit<T: Serialize>(data: &T) -> Result<()> {
+    let env = ENV.get();
+    commit_context(env);
+    match env.leaves {
+        Some(_) => commit_entry(env, data, None),
+        None => env.try_commit(data),
+    }
+}
+
+/// Commit a slice of plain-old-data values to the journal, copying their
+/// bytes instead of serializing each value.
+///
+/// The journal gets the number of values as a word, followed by their bytes
+/// padded with zeros to a whole number of words. For `u32`, this is the same
+/// as committing a `Vec<u32>`.
+pub fn commit_slice<T: Pod>(data: &[T]) {
+    let env = ENV.get();
+    commit_context(env);
+    let data = PodSlice::new(data);
+    match env.leaves {
+        Some(_) => commit_entry(env, &data, None),
+        None => env.try_commit(&data),
+    }
+    .unwrap();
+}
+
+/// Choose how the receipt commits to the journal; see [JournalMode].
+///
+/// Panics if anything has been committed already.
+pub fn set_journal_mode(mode: JournalMode) {
+    let env = ENV.get();
+    assert!(
+        env.commit_remaining() == env.commit_capacity * WORD_SIZE,
+        "The journal mode must be set before anything is committed"
+    );
+    env.leaves = match mode {
+        JournalMode::Whole => None,
+        JournalMode::Merkle => Some(Vec::new()),
+    };
+}
+
+/// Commit public data to the journal as an entry salted with `salt`, which
+/// hides the data from a verifier who isn't shown the entry, even if it could
+/// otherwise be guessed.
+///
+/// The salt should be random and kept secret, so it is usually provided by
+/// the host. Panics if the journal isn't in [JournalMode::Merkle], or if the
+/// data can't be serialized.
+pub fn commit_salted<T: Serialize>(data: &T, salt: &Digest) {
+    let env = ENV.get();
+    assert!(
+        env.leaves.is_some(),
+        "Salted commits need JournalMode::Merkle"
+    );
+    commit_context(env);
+    commit_entry(env, data, Some(salt)).unwrap();
+}
+
+// Commits the context the host supplied, if any and if it hasn't been
+// committed yet, so that it comes before anything the guest commits: as the
+// first 8 words of the journal, or as its first entry in Merkle mode.
+pub(crate) fn commit_context(env: &mut Env) {
+    if env.context_committed {
+        return;
+    }
+    env.context_committed = true;
+    if let Some(context) = env.startup().context {
+        match env.leaves {
+            Some(_) => commit_entry(env, &context, None),
+            None => env.try_commit(&context),
+        }
+        .unwrap();
+    }
+}
+
+/// Returns the context the host supplied with `Prover::set_context`, such as
+/// a nonce or the digest of a request, if any.
+///
+/// The context is committed to the journal ahead of anything the guest
+/// commits, so a verifier can check which request a receipt answers with
+/// `Receipt::verify_with_context`; the guest doesn't need to commit it.
+pub fn context() -> Option<&'static Digest> {
+    ENV.get().startup().context.as_ref()
+}
+
+// Commits the data as a journal entry, and adds its leaf.
+fn commit_entry<T: Payload + ?Sized>(env: &mut Env, data: &T, salt: Option<&Digest>) -> Result<()> {
+    let data = data.to_words()?;
+    let mut entry = Vec::with_capacity(1 + DIGEST_WORDS + data.len());
+    match salt {
+        Some(salt) => {
+            entry.push(data.len() as u32 | ENTRY_SALTED);
+            entry.extend_from_slice(salt.get());
+        }
+        None => entry.push(data.len() as u32),
+    }
+    entry.extend_from_slice(&data);
+    env.try_commit(&Words(&entry))?;
+    let leaf = sha::digest_u8_slice(bytemuck::cast_slice(&entry));
+    env.leaves.as_mut().unwrap().push(leaf);
+    Ok(())
+}
+
+// Something to write to the host or commit to the journal.
+pub(crate) trait Payload {
+    // Returns the number of words the payload takes.
+    fn word_len(&self) -> Result<usize>;
+
+    fn serialize_into<W: StreamWriter>(&self, serializer: &mut Serializer<W>) -> Result<()>;
+
+    fn to_words(&self) -> Result<Vec<u32>> {
+        let mut serializer = Serializer::new(AllocVec::with_capacity(self.word_len()?));
+        self.serialize_into(&mut serializer)?;
+        serializer.release()
+    }
+}
+
+impl<T: Serialize + ?Sized> Payload for T {
+    fn word_len(&self) -> Result<usize> {
+        serialized_len(self)
+    }
+
+    fn serialize_into<W: StreamWriter>(&self, serializer: &mut Serializer<W>) -> Result<()> {
+        self.serialize(serializer)
+    }
+}
+
+// Plain-old-data values, written as their number followed by their bytes.
+pub(crate) struct PodSlice<'a> {
+    len: u32,
+    bytes: &'a [u8],
+}
+
+impl<'a> PodSlice<'a> {
+    fn new<T: Pod>(data: &'a [T]) -> Self {
+        PodSlice {
+            len: data.len() as u32,
+            bytes: bytemuck::cast_slice(data),
+        }
+    }
+}
+
+impl Payload for PodSlice<'_> {
+    fn word_len(&self) -> Result<usize> {
+        Ok(1 + align_up(self.bytes.len(), WORD_SIZE) / WORD_SIZE)
+    }
+
+    fn serialize_into<W: StreamWriter>(&self, serializer: &mut Serializer<W>) -> Result<()> {
+        serializer.serialize_u32(self.len)?;
+        serializer.try_extend(self.bytes)
+    }
+}
+
+// Computes the Merkle root of the given journal entry leaves. This runs once,
+// at the end of the execution, so the accelerator's retained memory is fine.
+pub(crate) fn merkle_root(leaves: &[Digest]) -> Digest {
+    *MerkleTree::new(&sha::AcceleratedImpl {}, leaves).root()
 }
 
+// Serializes words as they are, without a length.
+struct Words<'a>(&'a [u32]);
+
+impl Serialize for Words<'_> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(self.0.len())?;
+        for word in self.0 {
+            tuple.serialize_element(word)?;
+        }
+        tuple.end()
+    }
+}
+
+/// Returns the number of bytes which can still be committed before the
+/// journal outgrows the commit region.
+///
+/// The journal may grow beyond this, but from then on it is copied to the
+/// heap, where it stays for the rest of the execution, so it can be hashed
+/// with the SHA accelerator when the guest exits.
+pub fn commit_remaining() -> usize {
+    ENV.get().commit_remaining()
+}
+
+/// Keeps only the first `words` words of the journal in the commit region, so
+/// that the journal outgrows it sooner, and moves to the heap from then on as
+/// if it had outgrown the whole region.
+///
+/// This is only for testing that path with a small journal. Panics if
+/// anything has been committed already, or if `words` is less than 8 or more
+/// than the region holds.
+#[doc(hidden)]
+pub fn set_commit_capacity(words: usize) {
+    let env = ENV.get();
+    assert!(
+        env.commit_remaining() == env.commit_capacity * WORD_SIZE,
+        "The commit capacity must be set before anything is committed"
+    );
+    // A journal which outgrows the commit region is always hashed, so it must
+    // be too long to be returned as it is.
+    assert!(
+        (DIGEST_WORDS..=COMMIT_CAPACITY).contains(&words),
+        "Invalid commit capacity: {}",
+        words
+    );
+    env.commit_capacity = words;
+}
+
+/// Open a file attached by the host, returning its contents.
+///
+/// Panics if there is no file with the given name; see [try_open].
+pub fn open(name: &str) -> &'static [u8] {
+    match try_open(name) {
+        Some(data) => data,
+        None => panic!("File not found: {}", name),
+    }
+}
+
+/// Open a file attached by the host, returning its contents, or `None` if
+/// there is no file with the given name.
+///
+/// If [set_commit_file_digests] is enabled, the name of the file and the
+/// SHA-256 digest of its contents are committed to the journal, each time it
+/// is opened.
+pub fn try_open(name: &str) -> Option<&'static [u8]> {
+    let env = ENV.get();
+    let (_, data) = *env.startup().files.iter().find(|(file, _)| *file == name)?;
+    if env.commit_file_digests {
+        commit(&(name, sha::digest_u8_slice(data)));
+    }
+    Some(data)
+}
+
+/// Returns the arguments the host started the guest with.
+pub fn args() -> &'static [&'static str] {
+    &ENV.get().startup().args
+}
+
+/// Returns the value of the environment variable `key` set by the host, or
+/// `None` if it isn't set.
+pub fn var(key: &str) -> Option<&'static str> {
+    vars()
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map(|(_, value)| *value)
+}
+
+/// Returns the environment variables set by the host, as (key, value) pairs.
+pub fn vars() -> &'static [(&'static str, &'static str)] {
+    &ENV.get().startup().vars
+}
+
+/// Choose whether [open] commits the name and SHA-256 digest of each file it
+/// opens to the journal, so the verifier learns which inputs were used.
+///
+/// This is disabled by default.
+pub fn set_commit_file_digests(enable: bool) {
+    ENV.get().commit_file_digests = enable;
+}
+
+#[cfg(target_arch = "riscv32")]
 impl Env {
     fn new() -> Self {
+        let layout = crate::layout();
         Env {
-            input: Deserializer::new(unsafe {
-                slice::from_raw_parts(REGION_INPUT_START as _, REGION_INPUT_LEN / WORD_SIZE)
-            }),
+            input: Deserializer::new(input_region()),
             output: Serializer::new(Slice::new(unsafe {
-                slice::from_raw_parts_mut(REGION_OUTPUT_START as _, REGION_OUTPUT_LEN / WORD_SIZE)
+                slice::from_raw_parts_mut(
+                    layout.output.start() as _,
+                    layout.output.len() / WORD_SIZE,
+                )
             })),
+            output_len: 0,
             commit: Serializer::new(Slice::new(unsafe {
-                slice::from_raw_parts_mut(REGION_COMMIT_START as _, REGION_COMMIT_LEN / WORD_SIZE)
+                slice::from_raw_parts_mut(layout.commit.start() as _, COMMIT_CAPACITY)
             })),
             commit_len: 0,
+            commit_capacity: COMMIT_CAPACITY,
+            overflow: None,
+            startup: None,
+            commit_file_digests: false,
+            context_committed: false,
+            leaves: None,
         }
     }
 
-    fn read<T: Deserialize<'static>>(&mut self) -> T {
-        T::deserialize(&mut self.input).unwrap()
+    fn startup(&mut self) -> &Startup {
+        // The startup table is only decoded if the guest uses it.
+        self.startup
+            .get_or_insert_with(|| decode_startup(input_region()))
     }
 
-    fn write<T: Serialize>(&mut self, data: &T) {
-        data.serialize(&mut self.output).unwrap();
-        let buf = self.output.release().unwrap();
-        unsafe {
-            let ptr = buf.as_ptr();
-            crate::memory_barrier(ptr);
-            GPIO_DESC_IO.write_volatile(IoDescriptor {
-                size: buf.len() * WORD_SIZE,
-                addr: ptr as usize,
-            });
-            GPIO_WRITE.write_volatile(GPIO_DESC_IO);
+    fn try_read_words<T: Deserialize<'static>>(&mut self) -> Result<(T, &'static [u32])> {
+        let saved = self.input.remaining();
+        match T::deserialize(&mut self.input) {
+            Ok(value) => {
+                let len = saved.len() - self.input.remaining().len();
+                Ok((value, &saved[..len]))
+            }
+            Err(err) => {
+                self.input = Deserializer::new(saved);
+                Err(err)
+            }
         }
     }
 
-    fn commit<T: Serialize>(&mut self, data: &T) {
-        data.serialize(&mut self.commit).unwrap();
-        let buf = self.commit.release().unwrap();
+    fn take_words(&mut self, len: usize) -> Option<&'static [u32]> {
+        let remaining = self.input.remaining();
+        let words = remaining.get(..len)?;
+        self.input = Deserializer::new(&remaining[len..]);
+        Some(words)
+    }
+
+    fn try_write_to<T: Payload + ?Sized>(&mut self, channel: u32, data: &T) -> Result<()> {
+        // The output region is write-once, so make sure the data fits before
+        // writing any of it.
+        let len = data.word_len()?;
+        if len > crate::layout().output.len() / WORD_SIZE - self.output_len {
+            return Err(Error::SerializeBufferFull);
+        }
+        data.serialize_into(&mut self.output)?;
+        let buf = self.output.release()?;
+        self.output_len += buf.len();
+        match channel {
+            0 => send(GPIO_WRITE, buf),
+            _ => send_channel(channel, buf),
+        }
+        Ok(())
+    }
+
+    fn try_commit<T: Payload + ?Sized>(&mut self, data: &T) -> Result<()> {
+        // The commit region is write-once, so measure the data before writing
+        // any of it.  Once the journal no longer fits, it moves to the heap.
+        let len = data.word_len()?;
+        if self.overflow.is_none() && len > self.commit_capacity - self.commit_len {
+            self.start_overflow(len);
+        }
+        if let Some(overflow) = &mut self.overflow {
+            // Nothing is sent to the host until the data has been serialized,
+            // so a failure can be undone.
+            let start = overflow.words.len();
+            let mut serializer = Serializer::new(&mut *overflow);
+            if let Err(err) = data.serialize_into(&mut serializer) {
+                overflow.words.truncate(start);
+                return Err(err);
+            }
+            serializer.release()?;
+            self.commit_len += len;
+            return Ok(());
+        }
+        data.serialize_into(&mut self.commit)?;
+        let buf = self.commit.release()?;
         self.commit_len += buf.len();
-        let len_bytes = buf.len() * WORD_SIZE;
+        send(GPIO_WRITE, buf);
+        Ok(())
+    }
+
+    fn commit_remaining(&self) -> usize {
+        match self.overflow {
+            Some(_) => 0,
+            None => (self.commit_capacity - self.commit_len) * WORD_SIZE,
+        }
+    }
+
+    // Moves the journal out of the commit region, before a commit of `len`
+    // words: what has been committed so far is sent to the host and copied to
+    // the heap, and later commits are sent as they are made.
+    fn start_overflow(&mut self, len: usize) {
+        let committed: &[u32] =
+            unsafe { slice::from_raw_parts(crate::layout().commit.start() as _, self.commit_len) };
+        send(GPIO_COMMIT, committed);
+        self.overflow = Some(Overflow::new(committed, len));
+    }
+
+    fn exit(&mut self, code: u32) -> ! {
+        extern "C" {
+            static mut __result: usize;
+        }
         unsafe {
-            let ptr = buf.as_ptr();
-            crate::memory_barrier(ptr);
-            GPIO_DESC_IO.write_volatile(IoDescriptor {
-                size: len_bytes,
-                addr: ptr as usize,
-            });
-            GPIO_WRITE.write_volatile(GPIO_DESC_IO);
+            self.finalize(core::ptr::addr_of_mut!(__result), code);
+            crate::_halt()
         }
     }
 
-    fn finalize(&mut self, result: *mut usize) {
+    fn finalize(&mut self, result: *mut usize, code: u32) {
         let len_words = self.commit_len;
         let len_bytes = len_words * WORD_SIZE;
-        let slice: &mut [u32] = unsafe {
-            slice::from_raw_parts_mut(REGION_COMMIT_START as _, REGION_COMMIT_LEN / WORD_SIZE)
+        if code == 0 {
+            assert!(len_bytes < JOURNAL_CONTEXT as usize, "Journal too large");
+        }
+
+        // A failure always hashes the journal, even in Merkle mode.
+        let root = match &self.leaves {
+            Some(leaves) if code == 0 => Some(merkle_root(leaves)),
+            _ => None,
         };
 
-        // Write the full data out to the host
-        unsafe {
-            let ptr = slice.as_ptr();
-            crate::memory_barrier(ptr);
-            GPIO_DESC_IO.write_volatile(IoDescriptor {
-                size: len_bytes,
-                addr: ptr as usize,
-            });
-            GPIO_COMMIT.write_volatile(GPIO_DESC_IO);
-        }
-
-        // If the total proof message is small (<= 32 bytes), return it directly
-        // from the proof, otherwise SHA it and return the hash.
-        if len_words <= 8 {
-            for i in 0..len_words {
-                unsafe {
-                    result
-                        .add(i)
-                        .write_volatile(*slice.get_unchecked(i) as usize)
-                };
-            }
-            for i in len_words..8 {
-                unsafe { result.add(i).write_volatile(0) };
+        if let Some(overflow) = &mut self.overflow {
+            // The journal has already been sent to the host.
+            match &root {
+                Some(root) => {
+                    for i in 0..DIGEST_WORDS {
+                        unsafe { result.add(i).write_volatile(root.get()[i] as usize) };
+                    }
+                }
+                // SAFETY: result is a pointer to the output digest.
+                None => unsafe { overflow.finish(result as *mut Digest) },
             }
         } else {
-            let cap = sha::compute_capacity_needed(len_bytes);
-            let mut slice = &mut slice[..cap];
-            sha::add_trailer(&mut slice, len_bytes, sha::MemoryType::WOM);
-
-            let digest = result as *mut Digest;
-            // SAFETY: result is a pointer to the output digest.
-            unsafe {
-                sha::raw_digest_to(&slice, digest);
+            let slice: &mut [u32] = unsafe {
+                slice::from_raw_parts_mut(
+                    crate::layout().commit.start() as _,
+                    REGION_COMMIT_LEN / WORD_SIZE,
+                )
+            };
+
+            // Write the full data out to the host
+            send(GPIO_COMMIT, &slice[..len_words]);
+
+            // If the total proof message is small (<= 32 bytes), return it directly
+            // from the proof, otherwise SHA it and return the hash.  The
+            // message is always hashed for a failure, since the length isn't
+            // part of the output.
+            if let Some(root) = &root {
+                for i in 0..DIGEST_WORDS {
+                    unsafe { result.add(i).write_volatile(root.get()[i] as usize) };
+                }
+            } else if len_words <= 8 && code == 0 {
+                for i in 0..len_words {
+                    unsafe {
+                        result
+                            .add(i)
+                            .write_volatile(*slice.get_unchecked(i) as usize)
+                    };
+                }
+                for i in len_words..8 {
+                    unsafe { result.add(i).write_volatile(0) };
+                }
+            } else {
+                let cap = sha::compute_capacity_needed(len_bytes);
+                let mut slice = &mut slice[..cap];
+                sha::add_trailer(&mut slice, len_bytes, sha::MemoryType::WOM);
+
+                let digest = result as *mut Digest;
+                // SAFETY: result is a pointer to the output digest.
+                unsafe {
+                    sha::raw_digest_to(&slice, digest);
+                }
             }
         }
+        // commit_context has decoded the startup table and committed the
+        // context, if there is one. The flags say how the journal is laid
+        // out, even for a failure, whose journal is always hashed.
+        let context = match &self.startup {
+            Some(Startup {
+                context: Some(_), ..
+            }) => JOURNAL_CONTEXT as usize,
+            _ => 0,
+        };
+        let merkle = match &self.leaves {
+            Some(_) => MERKLE_JOURNAL as usize,
+            None => 0,
+        };
+        let last = match code {
+            0 => merkle | context | len_bytes,
+            code => (EXIT_FAILURE | code) as usize | merkle | context,
+        };
         unsafe {
-            result.add(8).write_volatile(len_bytes);
+            result.add(8).write_volatile(last);
             crate::memory_barrier(result);
         };
         sha::finalize();
     }
 }
+
+// Returns the input region, which the host fills where the method's layout
+// puts it.
+#[cfg(target_arch = "riscv32")]
+fn input_region() -> &'static [u32] {
+    let input = crate::layout().input;
+    unsafe { slice::from_raw_parts(input.start() as _, input.len() / WORD_SIZE) }
+}
+
+// Sends the given words to the host through the given GPIO.
+fn send(gpio: *mut *const IoDescriptor, buf: &[u32]) {
+    unsafe {
+        let ptr = buf.as_ptr();
+        crate::memory_barrier(ptr);
+        GPIO_DESC_IO.write_volatile(IoDescriptor {
+            size: (buf.len() * WORD_SIZE) as u32,
+            addr: ptr as u32,
+        });
+        gpio.write_volatile(GPIO_DESC_IO);
+    }
+}
+
+// Sends the given words to the host on the given output channel.
+fn send_channel(channel: u32, buf: &[u32]) {
+    unsafe {
+        let ptr = buf.as_ptr();
+        crate::memory_barrier(ptr);
+        GPIO_DESC_CHANNEL.write_volatile(ChannelDescriptor {
+            channel,
+            size: (buf.len() * WORD_SIZE) as u32,
+            addr: ptr as u32,
+        });
+        GPIO_WRITE_CHANNEL.write_volatile(GPIO_DESC_CHANNEL);
+    }
+}
+
+// Holds the journal once it no longer fits in the commit region.
+//
+// The SHA accelerator starts each request from the initial state, and checks
+// it against the final contents of memory, so it can't continue a hash from
+// one commit to the next.  Instead, the journal is copied to the heap, where
+// it stays for the rest of the execution, and hashed in a single request when
+// the guest exits.  Each commit is sent to the host as soon as it is made.
+struct Overflow {
+    words: Vec<u32>,
+    // Number of words which have been sent to the host.
+    sent: usize,
+}
+
+impl Overflow {
+    // Starts with words which the host already has, leaving room for a
+    // commit of `len` words and the SHA trailer.
+    fn new(committed: &[u32], len: usize) -> Self {
+        let cap = sha::compute_capacity_needed((committed.len() + len) * WORD_SIZE);
+        let mut words = Vec::with_capacity(cap);
+        words.extend_from_slice(committed);
+        Overflow {
+            words,
+            sent: committed.len(),
+        }
+    }
+
+    // Sends any words not yet sent to the host through GPIO_COMMIT, and
+    // echoes them through GPIO_WRITE like any other commit.
+    fn flush(&mut self) {
+        let words = &self.words[self.sent..];
+        if !words.is_empty() {
+            send(GPIO_COMMIT, words);
+            send(GPIO_WRITE, words);
+        }
+        self.sent = self.words.len();
+    }
+
+    // Adds the SHA trailer and stores the digest of the journal in the given
+    // pointer, which must be uninitialized.
+    unsafe fn finish(&mut self, digest: *mut Digest) {
+        let len_bytes = self.words.len() * WORD_SIZE;
+        let cap = sha::compute_capacity_needed(len_bytes);
+        // Nothing on the heap is freed, so don't let resize double the
+        // journal.
+        self.words.reserve_exact(cap - self.words.len());
+        self.words.resize(cap, 0);
+        sha::add_trailer(&mut self.words, len_bytes, sha::MemoryType::Normal);
+        sha::raw_digest_to(mem::take(&mut self.words).leak(), digest);
+    }
+}
+
+impl StreamWriter for &mut Overflow {
+    type Output = ();
+
+    fn try_push_word(&mut self, data: u32) -> Result<()> {
+        self.words.push(data);
+        Ok(())
+    }
+
+    fn try_extend(&mut self, data: &[u8]) -> Result<()> {
+        // Copy the bytes straight in rather than a word at a time, since this
+        // is how large slices are committed.
+        let len = self.words.len();
+        let len_words = align_up(data.len(), WORD_SIZE) / WORD_SIZE;
+        self.words.reserve(len_words);
+        unsafe {
+            let dst = self.words.as_mut_ptr().add(len);
+            // Zero the last word, which data may only partly fill.
+            if len_words > 0 {
+                dst.add(len_words - 1).write(0);
+            }
+            ptr::copy_nonoverlapping(data.as_ptr(), dst as *mut u8, data.len());
+            self.words.set_len(len + len_words);
+        }
+        Ok(())
+    }
+
+    fn release(&mut self) -> Result<()> {
+        self.flush();
+        Ok(())
+    }
+}
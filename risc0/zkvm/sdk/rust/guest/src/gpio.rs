@@ -12,36 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-pub(crate) const GPIO_SHA: *mut *const SHADescriptor = 0x001C_0000 as _;
-pub(crate) const GPIO_WRITE: *mut *const IoDescriptor = 0x001C_0004 as _;
-pub(crate) const GPIO_COMMIT: *mut *const IoDescriptor = 0x001C_0008 as _;
-pub(crate) const GPIO_FAULT: *mut *const FaultDescriptor = 0x001C_000C as _;
-pub(crate) const GPIO_LOG: *mut *const LogDescriptor = 0x001C_0010 as _;
+use risc0_zkvm_platform::io;
+pub(crate) use risc0_zkvm_platform::io::{
+    ChannelDescriptor, FaultDescriptor, IoDescriptor, LogDescriptor, ShaDescriptor,
+};
 
-pub(crate) const GPIO_DESC_IO: *mut IoDescriptor = 0x001D_0000 as _;
-pub(crate) const GPIO_DESC_LOG: *mut LogDescriptor = 0x001D_0000 as _;
-pub(crate) const GPIO_DESC_FAULT: *mut FaultDescriptor = 0x001D_0000 as _;
+pub(crate) const GPIO_SHA: *mut *const ShaDescriptor = io::GPIO_SHA as _;
+pub(crate) const GPIO_WRITE: *mut *const IoDescriptor = io::GPIO_WRITE as _;
+pub(crate) const GPIO_COMMIT: *mut *const IoDescriptor = io::GPIO_COMMIT as _;
+pub(crate) const GPIO_FAULT: *mut *const FaultDescriptor = io::GPIO_FAULT as _;
+pub(crate) const GPIO_LOG: *mut *const LogDescriptor = io::GPIO_LOG as _;
+pub(crate) const GPIO_WRITE_CHANNEL: *mut *const ChannelDescriptor = io::GPIO_WRITE_CHANNEL as _;
 
-#[repr(C)]
-pub(crate) struct IoDescriptor {
-    pub size: usize,
-    pub addr: usize,
-}
-
-#[repr(C)]
-pub(crate) struct FaultDescriptor {
-    pub addr: usize,
-}
-
-#[repr(C)]
-pub(crate) struct LogDescriptor {
-    pub addr: usize,
-}
-
-#[repr(C)]
-pub(crate) struct SHADescriptor {
-    pub type_count: usize,
-    pub idx: usize,
-    pub source: usize,
-    pub digest: usize,
-}
+pub(crate) const GPIO_DESC_IO: *mut IoDescriptor = io::GPIO_DESC as _;
+pub(crate) const GPIO_DESC_LOG: *mut LogDescriptor = io::GPIO_DESC as _;
+pub(crate) const GPIO_DESC_FAULT: *mut FaultDescriptor = io::GPIO_DESC as _;
+pub(crate) const GPIO_DESC_CHANNEL: *mut ChannelDescriptor = io::GPIO_DESC as _;
@@ -20,58 +20,78 @@
 #![cfg_attr(target_arch = "riscv32", feature(new_uninit))]
 
 extern crate alloc as _alloc;
+#[cfg(not(target_arch = "riscv32"))]
+extern crate std;
 
 mod alloc;
 
+/// Fixed-width unsigned integers and Montgomery modular arithmetic.
+pub mod bigint;
+
 /// Functions for interacting with the host environment.
 pub mod env;
 
 mod gpio;
 
+mod memops;
+
+/// Native execution of guest code on the host, for testing.
+#[cfg(not(target_arch = "riscv32"))]
+pub mod native;
+
 /// Functions for computing SHA-256 hashes.
 pub mod sha;
 
-use core::{arch::asm, mem, panic::PanicInfo, ptr};
-use gpio::{FaultDescriptor, GPIO_DESC_FAULT, GPIO_FAULT};
+/// Verification of Ed25519 and ECDSA signatures.
+pub mod sig;
 
-const REGION_SIZE_256KB: usize = 256 * 1024;
-// const REGION_SIZE_512KB: usize = 0x0008_0000;
-const REGION_SIZE_1MB: usize = 1024 * 1024;
+/// Support for running tests inside the ZKVM.
+pub mod test_runner;
 
-const REGION_HEAP_START: usize = 0x0008_0000;
-const REGION_HEAP_LEN: usize = REGION_SIZE_1MB + REGION_SIZE_256KB;
-const REGION_HEAP_END: usize = REGION_HEAP_START + REGION_HEAP_LEN;
-
-const REGION_SHA_START: usize = 0x0030_0000;
-// const REGION_SHA_LEN: usize = REGION_SIZE_256KB;
-// const REGION_SHA_END: usize = REGION_SHA_START + REGION_SHA_LEN;
+use core::{arch::asm, panic::PanicInfo, ptr};
+use gpio::{FaultDescriptor, GPIO_DESC_FAULT, GPIO_FAULT};
+use risc0_zkvm_platform::{
+    memory::{self, Layout},
+    WORD_SIZE,
+};
 
-const REGION_INPUT_START: usize = 0x0018_0000;
-const REGION_INPUT_LEN: usize = REGION_SIZE_256KB;
-// const REGION_INPUT_END: usize = REGION_INPUT_START + REGION_INPUT_LEN;
+const REGION_SHA_START: usize = memory::SHA.start();
+const REGION_SHA_LEN: usize = memory::SHA.len();
 
-const REGION_OUTPUT_START: usize = 0x0034_0000;
-const REGION_OUTPUT_LEN: usize = REGION_SIZE_256KB;
+const REGION_COMMIT_LEN: usize = memory::COMMIT.len();
 
-const REGION_COMMIT_START: usize = 0x0038_0000;
-const REGION_COMMIT_LEN: usize = REGION_SIZE_256KB;
+// Returns the memory layout the method was linked with, which the linker
+// script places at `__layout`.
+#[cfg(target_arch = "riscv32")]
+pub(crate) fn layout() -> &'static Layout {
+    extern "C" {
+        static __layout: Layout;
+    }
+    unsafe { &__layout }
+}
 
-const WORD_SIZE: usize = mem::size_of::<u32>();
+#[cfg(not(target_arch = "riscv32"))]
+pub(crate) fn layout() -> &'static Layout {
+    &Layout::DEFAULT
+}
 
 extern "C" {
     fn _fault() -> !;
+    fn _halt() -> !;
 }
 
 #[cfg(target_arch = "riscv32")]
 #[panic_handler]
 unsafe fn panic_fault(panic_info: &PanicInfo<'static>) -> ! {
+    test_runner::fail(panic_info);
+
     let msg = _alloc::format!("{}\0", panic_info);
 
     let ptr = msg.as_ptr();
     crate::memory_barrier(ptr);
     GPIO_DESC_FAULT.write_volatile(FaultDescriptor {
-        // addr: "panic\0".as_ptr() as usize,
-        addr: ptr as usize,
+        // addr: "panic\0".as_ptr() as u32,
+        addr: ptr as u32,
     });
     // A compliant host should fault when it receives this descriptor.
     GPIO_FAULT.write_volatile(GPIO_DESC_FAULT);
@@ -102,6 +122,35 @@ macro_rules! entry {
     };
 }
 
+/// Used for defining a method which runs tests inside the ZKVM.
+///
+/// Each execution of the method runs one of the given test functions, as
+/// chosen by the host; see [test_runner::run]. The host runs all of them with
+/// `risc0_zkvm_host::test_runner::run_tests`.
+///
+/// # Example
+///
+/// ```
+/// risc0_zkvm_guest::test_entry!(adds, multiplies);
+///
+/// fn adds() {
+///     assert_eq!(2 + 2, 4);
+/// }
+///
+/// fn multiplies() {
+///     assert_eq!(2 * 3, 6);
+/// }
+/// ```
+#[macro_export]
+macro_rules! test_entry {
+    ($($path:path),* $(,)?) => {
+        #[no_mangle]
+        fn __main() {
+            $crate::test_runner::run(&[$((stringify!($path), $path as fn())),*])
+        }
+    };
+}
+
 #[cfg(target_arch = "riscv32")]
 #[no_mangle]
 unsafe extern "C" fn __start(result: *mut usize) {
@@ -141,6 +190,9 @@ _start:
     la sp, __stack_init$;
     la a0, __result
     jal ra, __start
+
+.globl _halt;
+_halt:
     la x10, __result
     lw x1, 0(x10)
     lw x2, 4(x10)
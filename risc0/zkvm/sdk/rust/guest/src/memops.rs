@@ -0,0 +1,251 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Replacements for the memory functions from compiler-builtins, which copy,
+// fill and compare a byte at a time. Those are weak symbols, so these take
+// their place when a guest is linked.
+//
+// In the ZKVM every instruction costs about a cycle, and a word load or store
+// costs the same as a byte one, so these work a word at a time wherever they
+// can, with the main loops unrolled to spread their overhead:
+//
+// * memcpy copies 32 bytes per iteration when the source and destination
+//   are equally aligned. Otherwise it loads aligned words from the source and
+//   shifts them into place, as the ZKVM faults on unaligned loads.
+// * memmove copies forwards with memcpy unless the destination overlaps the
+//   end of the source, in which case it copies backwards.
+// * memset stores 32 bytes per iteration.
+// * memcmp (and bcmp, which Rust uses for slice equality) compares a word at
+//   a time when the two sides are equally aligned, and finds the differing
+//   byte within a word only once the words differ.
+
+#[cfg(target_arch = "riscv32")]
+core::arch::global_asm!(
+    r#"
+.section .text.memcpy;
+.globl memcpy;
+memcpy:
+    mv t0, a0
+    add t1, a0, a2
+    li t2, 8
+    bltu a2, t2, .Lmemcpy_bytes
+.Lmemcpy_align:
+    andi t2, t0, 3
+    beqz t2, .Lmemcpy_aligned
+    lbu t2, 0(a1)
+    sb t2, 0(t0)
+    addi t0, t0, 1
+    addi a1, a1, 1
+    j .Lmemcpy_align
+.Lmemcpy_aligned:
+    andi t4, t1, -4
+    andi t2, a1, 3
+    bnez t2, .Lmemcpy_unaligned
+    li t6, 32
+.Lmemcpy_block:
+    sub t2, t4, t0
+    bltu t2, t6, .Lmemcpy_word
+    lw t2, 0(a1)
+    lw t3, 4(a1)
+    lw t5, 8(a1)
+    lw a3, 12(a1)
+    lw a4, 16(a1)
+    lw a5, 20(a1)
+    lw a6, 24(a1)
+    lw a7, 28(a1)
+    sw t2, 0(t0)
+    sw t3, 4(t0)
+    sw t5, 8(t0)
+    sw a3, 12(t0)
+    sw a4, 16(t0)
+    sw a5, 20(t0)
+    sw a6, 24(t0)
+    sw a7, 28(t0)
+    addi t0, t0, 32
+    addi a1, a1, 32
+    j .Lmemcpy_block
+.Lmemcpy_word:
+    beq t0, t4, .Lmemcpy_bytes
+    lw t2, 0(a1)
+    sw t2, 0(t0)
+    addi t0, t0, 4
+    addi a1, a1, 4
+    j .Lmemcpy_word
+.Lmemcpy_unaligned:
+    /* a3 = source offset in its word, t5 = aligned source. */
+    andi a3, a1, 3
+    sub t5, a1, a3
+    /* Shift by 8 * offset one way, and 32 - 8 * offset the other. */
+    slli a4, a3, 3
+    neg a5, a4
+    lw t2, 0(t5)
+.Lmemcpy_shift:
+    beq t0, t4, .Lmemcpy_shift_done
+    lw t3, 4(t5)
+    srl t2, t2, a4
+    sll a6, t3, a5
+    or t2, t2, a6
+    sw t2, 0(t0)
+    mv t2, t3
+    addi t5, t5, 4
+    addi t0, t0, 4
+    j .Lmemcpy_shift
+.Lmemcpy_shift_done:
+    add a1, t5, a3
+.Lmemcpy_bytes:
+    beq t0, t1, .Lmemcpy_done
+    lbu t2, 0(a1)
+    sb t2, 0(t0)
+    addi t0, t0, 1
+    addi a1, a1, 1
+    j .Lmemcpy_bytes
+.Lmemcpy_done:
+    ret
+
+.section .text.memmove;
+.globl memmove;
+memmove:
+    /* Copying forwards is safe unless dest is in (src, src + n). */
+    sub t2, a0, a1
+    bltu t2, a2, .Lmemmove_back
+    tail memcpy
+.Lmemmove_back:
+    add t0, a0, a2
+    add t1, a1, a2
+    xor t2, t0, t1
+    andi t2, t2, 3
+    bnez t2, .Lmemmove_bytes
+.Lmemmove_align:
+    andi t2, t0, 3
+    beqz t2, .Lmemmove_aligned
+    beq t0, a0, .Lmemmove_done
+    addi t0, t0, -1
+    addi t1, t1, -1
+    lbu t2, 0(t1)
+    sb t2, 0(t0)
+    j .Lmemmove_align
+.Lmemmove_aligned:
+    addi t4, a0, 3
+    andi t4, t4, -4
+.Lmemmove_word:
+    beq t0, t4, .Lmemmove_bytes
+    addi t0, t0, -4
+    addi t1, t1, -4
+    lw t2, 0(t1)
+    sw t2, 0(t0)
+    j .Lmemmove_word
+.Lmemmove_bytes:
+    beq t0, a0, .Lmemmove_done
+    addi t0, t0, -1
+    addi t1, t1, -1
+    lbu t2, 0(t1)
+    sb t2, 0(t0)
+    j .Lmemmove_bytes
+.Lmemmove_done:
+    ret
+
+.section .text.memset;
+.globl memset;
+memset:
+    mv t0, a0
+    add t1, a0, a2
+    li t2, 8
+    bltu a2, t2, .Lmemset_bytes
+.Lmemset_align:
+    andi t2, t0, 3
+    beqz t2, .Lmemset_aligned
+    sb a1, 0(t0)
+    addi t0, t0, 1
+    j .Lmemset_align
+.Lmemset_aligned:
+    /* Repeat the byte across a word. */
+    andi a1, a1, 0xff
+    slli t2, a1, 8
+    or a1, a1, t2
+    slli t2, a1, 16
+    or a1, a1, t2
+    andi t4, t1, -4
+    li t6, 32
+.Lmemset_block:
+    sub t2, t4, t0
+    bltu t2, t6, .Lmemset_word
+    sw a1, 0(t0)
+    sw a1, 4(t0)
+    sw a1, 8(t0)
+    sw a1, 12(t0)
+    sw a1, 16(t0)
+    sw a1, 20(t0)
+    sw a1, 24(t0)
+    sw a1, 28(t0)
+    addi t0, t0, 32
+    j .Lmemset_block
+.Lmemset_word:
+    beq t0, t4, .Lmemset_bytes
+    sw a1, 0(t0)
+    addi t0, t0, 4
+    j .Lmemset_word
+.Lmemset_bytes:
+    beq t0, t1, .Lmemset_done
+    sb a1, 0(t0)
+    addi t0, t0, 1
+    j .Lmemset_bytes
+.Lmemset_done:
+    ret
+
+.section .text.memcmp;
+.globl memcmp;
+.globl bcmp;
+memcmp:
+bcmp:
+    add t1, a0, a2
+    xor t2, a0, a1
+    andi t2, t2, 3
+    bnez t2, .Lmemcmp_bytes
+.Lmemcmp_align:
+    andi t2, a0, 3
+    beqz t2, .Lmemcmp_aligned
+    beq a0, t1, .Lmemcmp_equal
+    lbu t2, 0(a0)
+    lbu t3, 0(a1)
+    bne t2, t3, .Lmemcmp_differ
+    addi a0, a0, 1
+    addi a1, a1, 1
+    j .Lmemcmp_align
+.Lmemcmp_aligned:
+    andi t4, t1, -4
+.Lmemcmp_word:
+    beq a0, t4, .Lmemcmp_bytes
+    lw t2, 0(a0)
+    lw t3, 0(a1)
+    /* Find which byte of the word differs. */
+    bne t2, t3, .Lmemcmp_bytes
+    addi a0, a0, 4
+    addi a1, a1, 4
+    j .Lmemcmp_word
+.Lmemcmp_bytes:
+    beq a0, t1, .Lmemcmp_equal
+    lbu t2, 0(a0)
+    lbu t3, 0(a1)
+    bne t2, t3, .Lmemcmp_differ
+    addi a0, a0, 1
+    addi a1, a1, 1
+    j .Lmemcmp_bytes
+.Lmemcmp_differ:
+    sub a0, t2, t3
+    ret
+.Lmemcmp_equal:
+    li a0, 0
+    ret
+"#
+);
@@ -0,0 +1,674 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    boxed::Box,
+    cell::UnsafeCell,
+    collections::BTreeMap,
+    panic,
+    string::{String, ToString},
+    thread_local,
+    vec::Vec,
+};
+
+use risc0_zkvm_core::Digest;
+use risc0_zkvm_serde::{Deserializer, Error, Result};
+use serde::Deserialize;
+
+use crate::{
+    env::{commit_context, decode_startup, merkle_root, Payload, Startup, COMMIT_CAPACITY},
+    WORD_SIZE,
+};
+
+/// Runs guest code natively on the host, for testing.
+///
+/// Input is provided with [Runner::add_input], as with the host's `Prover`,
+/// and [Runner::run] calls the guest's `main` function on the current thread,
+/// collecting what it writes and commits. SHA-256 hashes are computed in
+/// software, and nothing is proven.
+///
+/// # Example
+///
+/// ```
+/// use risc0_zkvm_guest::{env, native::Runner};
+///
+/// fn guest_main() {
+///     let value: u32 = env::read();
+///     env::commit(&(value + 1));
+/// }
+///
+/// let mut runner = Runner::new();
+/// runner.add_input(&[41]);
+/// let session = runner.run(guest_main);
+/// assert_eq!(session.journal, [42]);
+/// ```
+#[derive(Default)]
+pub struct Runner {
+    input: Vec<u32>,
+    args: Vec<String>,
+    vars: Vec<(String, String)>,
+    files: Vec<(String, Vec<u8>)>,
+    context: Option<Digest>,
+}
+
+/// The result of running guest code with a [Runner].
+#[derive(Debug)]
+pub struct Session {
+    /// The words committed to the journal.
+    pub journal: Vec<u32>,
+
+    /// The private words written to the host.
+    pub output: Vec<u32>,
+
+    /// The private words written to each output channel other than 0, which
+    /// is [Session::output].
+    pub channels: BTreeMap<u32, Vec<u32>>,
+
+    /// The code the guest exited with, which is 0 unless it called
+    /// [crate::env::exit] with a failure.
+    pub exit_code: u32,
+
+    /// The Merkle root of the journal entries, if the guest used
+    /// [JournalMode::Merkle](crate::env::JournalMode::Merkle) and didn't exit
+    /// with a failure.
+    pub merkle_root: Option<Digest>,
+}
+
+impl Runner {
+    /// Create a new [Runner] with no input.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Provide private input data that is available to the guest to `read`.
+    pub fn add_input(&mut self, slice: &[u32]) {
+        self.input.extend_from_slice(slice);
+    }
+
+    /// Add arguments for the guest, which it gets from
+    /// [args](crate::env::args), as with the host's `Prover`.
+    pub fn args(&mut self, args: &[&str]) {
+        self.args.extend(args.iter().map(|arg| arg.to_string()));
+    }
+
+    /// Set an environment variable for the guest, which it gets from
+    /// [var](crate::env::var), as with the host's `Prover`.
+    pub fn env(&mut self, key: &str, value: &str) {
+        match self.vars.iter_mut().find(|(name, _)| name == key) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.vars.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    /// Attach a named file that the guest can [open](crate::env::open), as
+    /// with the host's `Prover`.
+    pub fn add_file(&mut self, name: &str, data: &[u8]) {
+        assert!(
+            self.files.iter().all(|(file, _)| file != name),
+            "Duplicate file: {}",
+            name
+        );
+        self.files.push((name.to_string(), data.to_vec()));
+    }
+
+    /// Set the context which is committed to the journal ahead of anything
+    /// the guest commits, and which it gets from
+    /// [context](crate::env::context), as with the host's `Prover`.
+    pub fn set_context(&mut self, context: Digest) {
+        self.context = Some(context);
+    }
+
+    // Lays out the startup table as the host's Prover does at the end of the
+    // input region, which decode_startup then reads back.
+    fn startup_table(&self) -> Vec<u32> {
+        let mut table = Vec::new();
+        if self.args.is_empty()
+            && self.vars.is_empty()
+            && self.files.is_empty()
+            && self.context.is_none()
+        {
+            table.push(0);
+            return table;
+        }
+        table.push(self.args.len() as u32);
+        for arg in &self.args {
+            push_bytes(&mut table, arg.as_bytes());
+        }
+        table.push(self.vars.len() as u32);
+        for (key, value) in &self.vars {
+            push_bytes(&mut table, key.as_bytes());
+            push_bytes(&mut table, value.as_bytes());
+        }
+        table.push(self.files.len() as u32);
+        for (name, data) in &self.files {
+            push_bytes(&mut table, name.as_bytes());
+            push_bytes(&mut table, data);
+        }
+        match &self.context {
+            Some(context) => {
+                table.push(1);
+                table.extend_from_slice(context.get());
+            }
+            None => table.push(0),
+        }
+        table.push(table.len() as u32);
+        table
+    }
+
+    /// Run the given guest `main` function.
+    ///
+    /// If the guest panics, the panic is propagated to the caller. The input
+    /// and startup table are leaked, since the guest can keep `'static`
+    /// borrows of them, such as a `&str` it read, beyond the run.
+    pub fn run(&self, main: fn()) -> Session {
+        // Values read by the guest may borrow from its input for the rest of
+        // the run, just as they would in the zkVM, and may outlive it.
+        let input = Vec::leak(self.input.clone());
+        let table = Vec::leak(self.startup_table());
+        let startup = Box::leak(Box::new(decode_startup(table)));
+        CURRENT.with(|env| unsafe { *env.get() = Some(Env::new(input, startup)) });
+        let result = panic::catch_unwind(main);
+        // The zkVM commits the context as it halts, if the guest didn't commit
+        // anything.
+        commit_context(ENV.get());
+        let env = CURRENT.with(|env| unsafe { (*env.get()).take() }).unwrap();
+        let exit_code = match result {
+            Ok(()) => 0,
+            Err(payload) => match payload.downcast::<Exit>() {
+                Ok(exit) => exit.0,
+                Err(payload) => panic::resume_unwind(payload),
+            },
+        };
+        let merkle_root = match env.leaves {
+            Some(leaves) if exit_code == 0 => Some(merkle_root(&leaves)),
+            _ => None,
+        };
+        Session {
+            journal: env.journal,
+            output: env.output,
+            channels: env.channels,
+            exit_code,
+            merkle_root,
+        }
+    }
+}
+
+// Appends the given bytes as their length followed by the bytes, padded to a
+// whole number of words.
+fn push_bytes(table: &mut Vec<u32>, bytes: &[u8]) {
+    table.push(bytes.len() as u32);
+    for chunk in bytes.chunks(WORD_SIZE) {
+        let mut word = [0; WORD_SIZE];
+        word[..chunk.len()].copy_from_slice(chunk);
+        table.push(u32::from_le_bytes(word));
+    }
+}
+
+// Unwinds from env::exit back to Runner::run.
+struct Exit(u32);
+
+thread_local! {
+    static CURRENT: UnsafeCell<Option<Env>> = UnsafeCell::new(None);
+}
+
+// Provides the environment of the guest running on the current thread.
+pub(crate) struct CurrentEnv;
+
+pub(crate) static ENV: CurrentEnv = CurrentEnv;
+
+impl CurrentEnv {
+    pub(crate) fn get(&self) -> &mut Env {
+        // SAFETY: The environment is only used by the current thread, and
+        // lives until Runner::run returns.
+        CURRENT
+            .with(|env| unsafe { (*env.get()).as_mut() })
+            .expect("Guest code must be run with risc0_zkvm_guest::native::Runner")
+    }
+}
+
+pub(crate) struct Env {
+    input: Deserializer<'static>,
+    output: Vec<u32>,
+    channels: BTreeMap<u32, Vec<u32>>,
+    output_len: usize,
+    journal: Vec<u32>,
+    startup: &'static Startup,
+    pub(crate) commit_capacity: usize,
+    pub(crate) commit_file_digests: bool,
+    pub(crate) context_committed: bool,
+    pub(crate) leaves: Option<Vec<Digest>>,
+}
+
+impl Env {
+    fn new(input: &'static [u32], startup: &'static Startup) -> Self {
+        Env {
+            input: Deserializer::new(input),
+            output: Vec::new(),
+            channels: BTreeMap::new(),
+            output_len: 0,
+            journal: Vec::new(),
+            startup,
+            commit_capacity: COMMIT_CAPACITY,
+            commit_file_digests: false,
+            context_committed: false,
+            leaves: None,
+        }
+    }
+
+    pub(crate) fn startup(&mut self) -> &Startup {
+        self.startup
+    }
+
+    pub(crate) fn try_read_words<T: Deserialize<'static>>(
+        &mut self,
+    ) -> Result<(T, &'static [u32])> {
+        let saved = self.input.remaining();
+        match T::deserialize(&mut self.input) {
+            Ok(value) => {
+                let len = saved.len() - self.input.remaining().len();
+                Ok((value, &saved[..len]))
+            }
+            Err(err) => {
+                self.input = Deserializer::new(saved);
+                Err(err)
+            }
+        }
+    }
+
+    pub(crate) fn take_words(&mut self, len: usize) -> Option<&'static [u32]> {
+        let remaining = self.input.remaining();
+        let words = remaining.get(..len)?;
+        self.input = Deserializer::new(&remaining[len..]);
+        Some(words)
+    }
+
+    pub(crate) fn try_write_to<T: Payload + ?Sized>(
+        &mut self,
+        channel: u32,
+        data: &T,
+    ) -> Result<()> {
+        // Enforce the size of the output region, which all channels share, as
+        // the zkVM does.
+        let len = data.word_len()?;
+        if len > crate::layout().output.len() / WORD_SIZE - self.output_len {
+            return Err(Error::SerializeBufferFull);
+        }
+        let buf = match channel {
+            0 => &mut self.output,
+            _ => self.channels.entry(channel).or_default(),
+        };
+        buf.extend(data.to_words()?);
+        self.output_len += len;
+        Ok(())
+    }
+
+    pub(crate) fn try_commit<T: Payload + ?Sized>(&mut self, data: &T) -> Result<()> {
+        self.journal.extend(data.to_words()?);
+        Ok(())
+    }
+
+    pub(crate) fn commit_remaining(&self) -> usize {
+        self.commit_capacity.saturating_sub(self.journal.len()) * WORD_SIZE
+    }
+
+    pub(crate) fn exit(&mut self, code: u32) -> ! {
+        panic::resume_unwind(Box::new(Exit(code)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkp_core::{
+        merkle::MerkleTree,
+        sha::{Digest, Sha},
+        sha_cpu,
+    };
+    use risc0_zkvm_serde::{from_slice, to_vec};
+    use std::{vec, vec::Vec};
+
+    use super::Runner;
+    use crate::{env, sha};
+
+    fn hash_input() {
+        let data: &[u8] = env::read();
+        let digest = sha::digest_u8_slice(data);
+        env::write(&data.len());
+        env::commit(&digest);
+    }
+
+    #[test]
+    fn digest() {
+        let mut runner = Runner::new();
+        runner.add_input(&to_vec(&"abc").unwrap());
+        let session = runner.run(hash_input);
+        assert_eq!(session.exit_code, 0);
+        assert_eq!(from_slice::<usize>(&session.output).unwrap(), 3);
+        assert_eq!(
+            from_slice::<Digest>(&session.journal).unwrap(),
+            Digest::new([
+                0xba7816bf, 0x8f01cfea, 0x414140de, 0x5dae2223, 0xb00361a3, 0x96177a9c, 0xb410ff61,
+                0xf20015ad
+            ])
+        );
+    }
+
+    fn write_channels() {
+        env::write(&1u32);
+        env::write_to(2, &"two");
+        env::write_to(3, &3u8);
+        env::write_to(2, &[2u32, 2]);
+        env::write_to(0, &0u32);
+    }
+
+    #[test]
+    fn channels() {
+        let session = Runner::new().run(write_channels);
+        assert_eq!(session.output, [1, 0]);
+        assert_eq!(session.channels.keys().collect::<Vec<_>>(), [&2, &3]);
+        assert_eq!(session.channels[&2], to_vec(&("two", [2u32, 2])).unwrap());
+        assert_eq!(session.channels[&3], [3]);
+    }
+
+    #[derive(Clone, Copy)]
+    #[repr(C)]
+    struct Point {
+        x: u32,
+        y: u16,
+        z: u16,
+    }
+
+    unsafe impl bytemuck::Zeroable for Point {}
+    unsafe impl bytemuck::Pod for Point {}
+
+    fn copy_pods() {
+        let point: &Point = env::read_pod();
+        let bytes: &[u8] = env::read_slice();
+        let words: &[u32] = env::read_slice();
+        env::commit_slice(&[*point, *point]);
+        env::commit_slice(bytes);
+        env::write_slice(words);
+        env::write(&env::read::<u32>());
+    }
+
+    #[test]
+    fn pods() {
+        let mut runner = Runner::new();
+        runner.add_input(&[1, 2 | 3 << 16]);
+        runner.add_input(&[5, u32::from_le_bytes(*b"abcd"), u32::from(b'e')]);
+        runner.add_input(&to_vec(&vec![6u32, 7]).unwrap());
+        runner.add_input(&[8]);
+        let session = runner.run(copy_pods);
+        assert_eq!(
+            session.journal,
+            [
+                2,
+                1,
+                2 | 3 << 16,
+                1,
+                2 | 3 << 16,
+                5,
+                u32::from_le_bytes(*b"abcd"),
+                101
+            ]
+        );
+        assert_eq!(
+            from_slice::<Vec<u32>>(&session.output[..3]).unwrap(),
+            [6, 7]
+        );
+        assert_eq!(session.output[3], 8);
+    }
+
+    #[test]
+    fn borrows_outlive_run() {
+        use std::{cell::Cell, thread_local};
+
+        thread_local! {
+            static KEPT: Cell<Option<(&'static str, &'static [&'static str])>> = Cell::new(None);
+        }
+
+        fn keep() {
+            KEPT.with(|kept| kept.set(Some((env::read(), env::args()))));
+        }
+
+        let mut runner = Runner::new();
+        runner.add_input(&to_vec(&"kept").unwrap());
+        runner.args(&["run"]);
+        runner.run(keep);
+        let (value, args) = KEPT.with(Cell::get).unwrap();
+        assert_eq!(value, "kept");
+        assert_eq!(args, ["run"]);
+    }
+
+    #[test]
+    fn sha_impl() {
+        risc0_zkp_core::sha::testutil::test_sha_impl(&sha::Impl {});
+        risc0_zkp_core::sha::testutil::test_sha_impl(&sha::AcceleratedImpl {});
+    }
+
+    #[test]
+    fn sha_to_caller_storage() {
+        let cpu = sha_cpu::Impl {};
+        let data = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+        let mut out = Digest::default();
+        sha::digest_u8_slice_to(data, &mut out);
+        assert_eq!(out, *cpu.hash_bytes(data));
+
+        let a = *cpu.hash_bytes(b"a");
+        let b = *cpu.hash_bytes(b"b");
+        sha::hash_pair_to(&a, &b, &mut out);
+        assert_eq!(out, *cpu.hash_pair(&a, &b));
+    }
+
+    fn exit_on_bad_input() {
+        match env::try_read::<u64>() {
+            Ok(value) => env::commit(&value),
+            Err(_) => {
+                env::commit(&"bad input");
+                env::exit(2);
+            }
+        }
+    }
+
+    #[test]
+    fn exit() {
+        let mut runner = Runner::new();
+        runner.add_input(&[7, 0]);
+        let session = runner.run(exit_on_bad_input);
+        assert_eq!(session.exit_code, 0);
+        assert_eq!(session.journal, [7, 0]);
+
+        let mut runner = Runner::new();
+        runner.add_input(&[7]);
+        let session = runner.run(exit_on_bad_input);
+        assert_eq!(session.exit_code, 2);
+        assert_eq!(from_slice::<&str>(&session.journal).unwrap(), "bad input");
+    }
+
+    fn open_files() {
+        env::set_commit_file_digests(true);
+        let names: Vec<&str> = env::read();
+        for name in names {
+            let data = env::try_open(name);
+            env::write(&data.map(|data| core::str::from_utf8(data).unwrap()));
+        }
+    }
+
+    #[test]
+    fn files() {
+        let mut runner = Runner::new();
+        runner.add_file("empty", b"");
+        runner.add_file("abc", b"abc");
+        runner.add_input(&to_vec(&["abc", "missing", "empty"].as_slice()).unwrap());
+        let session = runner.run(open_files);
+        assert_eq!(
+            from_slice::<[Option<&str>; 3]>(&session.output).unwrap(),
+            [Some("abc"), None, Some("")]
+        );
+        assert_eq!(
+            from_slice::<[(&str, Digest); 2]>(&session.journal).unwrap(),
+            [
+                ("abc", sha::digest_u8_slice(b"abc")),
+                ("empty", sha::digest_u8_slice(b"")),
+            ]
+        );
+    }
+
+    fn echo_args() {
+        env::commit(&env::args());
+        env::commit(&env::var("MODE"));
+        env::commit(&env::var("MISSING"));
+    }
+
+    #[test]
+    fn args() {
+        let mut runner = Runner::new();
+        runner.args(&["run", "--fast"]);
+        runner.env("MODE", "debug");
+        runner.env("MODE", "release");
+        let session = runner.run(echo_args);
+        assert_eq!(
+            from_slice::<(Vec<&str>, Option<&str>, Option<&str>)>(&session.journal).unwrap(),
+            (vec!["run", "--fast"], Some("release"), None)
+        );
+
+        let session = Runner::new().run(echo_args);
+        assert_eq!(
+            from_slice::<(Vec<&str>, Option<&str>, Option<&str>)>(&session.journal).unwrap(),
+            (vec![], None, None)
+        );
+    }
+
+    fn read_large() {
+        let expected: Digest = env::read();
+        let data: Vec<u32> = env::read_verified(&expected);
+        let data2: &str = env::read_and_commit_digest();
+        env::commit(&(data.len(), data2));
+    }
+
+    #[test]
+    fn read_verified() {
+        let data: Vec<u32> = (0..1000).collect();
+        let mut runner = Runner::new();
+        runner.add_input(&to_vec(&sha::digest(&data)).unwrap());
+        runner.add_input(&to_vec(&data).unwrap());
+        runner.add_input(&to_vec(&"abc").unwrap());
+        let session = runner.run(read_large);
+        assert_eq!(
+            from_slice::<(Digest, (usize, &str))>(&session.journal).unwrap(),
+            (sha::digest(&"abc"), (1000, "abc"))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough input for read_slice")]
+    fn read_slice_too_long() {
+        let mut runner = Runner::new();
+        runner.add_input(&[u32::MAX, 1, 2]);
+        runner.run(|| {
+            env::read_slice::<u64>();
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Input digest mismatch")]
+    fn read_verified_mismatch() {
+        let mut runner = Runner::new();
+        runner.add_input(&to_vec(&sha::digest(&"abc")).unwrap());
+        runner.add_input(&to_vec(&"abd").unwrap());
+        runner.run(|| {
+            env::read_verified::<&str>(&env::read());
+        });
+    }
+
+    fn merkle_journal() {
+        env::set_journal_mode(env::JournalMode::Merkle);
+        env::commit(&"public");
+        env::commit_salted(&7u32, &Digest::new([1, 2, 3, 4, 5, 6, 7, 8]));
+    }
+
+    #[test]
+    fn merkle() {
+        let session = Runner::new().run(merkle_journal);
+        let mut entries = Vec::new();
+        let public = to_vec(&"public").unwrap();
+        entries.push([&[public.len() as u32][..], &public].concat());
+        entries.push(vec![1 | 1 << 31, 1, 2, 3, 4, 5, 6, 7, 8, 7]);
+        assert_eq!(session.journal, entries.concat());
+
+        let sha = risc0_zkp_core::sha::default_implementation();
+        let leaves: Vec<Digest> = entries.iter().map(|entry| *sha.hash_words(entry)).collect();
+        let tree = MerkleTree::new(sha, &leaves);
+        assert_eq!(session.merkle_root, Some(*tree.root()));
+    }
+
+    #[test]
+    fn context() {
+        let context = Digest::new([8, 7, 6, 5, 4, 3, 2, 1]);
+        let mut runner = Runner::new();
+        runner.set_context(context);
+        let session = runner.run(|| {
+            assert_eq!(env::context(), Some(&Digest::new([8, 7, 6, 5, 4, 3, 2, 1])));
+            env::commit(&9u32);
+        });
+        assert_eq!(session.journal, [8, 7, 6, 5, 4, 3, 2, 1, 9]);
+
+        // The context is committed even if the guest commits nothing.
+        assert_eq!(runner.run(|| ()).journal, context.get());
+
+        // In Merkle mode, the context is the first entry.
+        let session = runner.run(merkle_journal);
+        assert_eq!(session.journal[..9], [8, 8, 7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(
+            session.journal[9..],
+            Runner::new().run(merkle_journal).journal
+        );
+
+        let session = Runner::new().run(|| assert_eq!(env::context(), None));
+        assert_eq!(session.journal, []);
+    }
+
+    #[test]
+    #[should_panic(expected = "The journal mode must be set before anything is committed")]
+    fn late_journal_mode() {
+        Runner::new().run(|| {
+            env::commit(&1u32);
+            env::set_journal_mode(env::JournalMode::Merkle);
+        });
+    }
+
+    #[test]
+    fn commit_capacity() {
+        let session = Runner::new().run(|| {
+            env::set_commit_capacity(8);
+            assert_eq!(env::commit_remaining(), 32);
+            env::commit(&[1u32; 6]);
+            assert_eq!(env::commit_remaining(), 8);
+            env::commit(&[2u32; 3]);
+            assert_eq!(env::commit_remaining(), 0);
+        });
+        assert_eq!(session.journal, [1, 1, 1, 1, 1, 1, 2, 2, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid commit capacity: 7")]
+    fn small_commit_capacity() {
+        Runner::new().run(|| env::set_commit_capacity(7));
+    }
+
+    #[test]
+    #[should_panic(expected = "Failure")]
+    fn panic() {
+        Runner::new().run(|| panic!("Failure"));
+    }
+}
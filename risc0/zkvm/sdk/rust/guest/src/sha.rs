@@ -13,17 +13,28 @@
 // limitations under the License.
 
 use _alloc::{boxed::Box, vec::Vec};
-use core::{cell::UnsafeCell, mem};
+use core::{
+    cell::UnsafeCell,
+    mem,
+    ops::{Deref, DerefMut},
+    slice,
+};
 
+use digest::{
+    consts::{U32, U64},
+    crypto_common::BlockSizeUser,
+    FixedOutput, FixedOutputReset, HashMarker, Output, OutputSizeUser, Reset, Update,
+};
 use risc0_zkp_core::{fp::Fp, fp4::Fp4};
 use risc0_zkvm_core::{Digest, DIGEST_WORDS};
 use risc0_zkvm_serde::to_vec_with_capacity;
 use serde::Serialize;
+use sha2::{compress256, digest::generic_array::GenericArray};
 
 use crate::{
     align_up,
-    gpio::{SHADescriptor, GPIO_SHA},
-    REGION_SHA_START, WORD_SIZE,
+    gpio::{ShaDescriptor, GPIO_SHA},
+    REGION_SHA_LEN, REGION_SHA_START, WORD_SIZE,
 };
 
 // Current sha descriptor index.
@@ -34,56 +45,130 @@ unsafe impl Sync for CurDesc {}
 
 static CUR_DESC: CurDesc = CurDesc(UnsafeCell::new(0));
 
-const END_MARKER: u8 = 0x80;
+pub(crate) const END_MARKER: u8 = 0x80;
+
+// The size of a SHA-256 block in bytes.
+const BLOCK_BYTES: usize = 64;
 
 // Chunk size in words for optimized SHA to operate on; all SHA
 // requests must be a multiple of this size.
-const CHUNK_SIZE: usize = 64 / WORD_SIZE;
+const CHUNK_SIZE: usize = BLOCK_BYTES / WORD_SIZE;
 
-fn alloc_desc() -> *mut SHADescriptor {
+// Number of descriptors which fit in the SHA region.
+//
+// The circuit walks the descriptors from the start of the region once
+// execution has finished, checking each one against the final contents
+// of memory, until it reaches the end marker.  Descriptors therefore
+// can't be reused, and the last one is reserved for the end marker.
+const MAX_DESCS: usize = REGION_SHA_LEN / mem::size_of::<ShaDescriptor>();
+
+// Allocates the next descriptor, or returns None once they have all
+// been used.
+fn alloc_desc() -> Option<*mut ShaDescriptor> {
     // SAFETY: Single threaded and this is the only place we use CUR_DESC.
     unsafe {
         let cur_desc = CUR_DESC.0.get();
-        let ptr = (REGION_SHA_START as *mut SHADescriptor).add(*cur_desc);
+        if *cur_desc + 1 >= MAX_DESCS {
+            return None;
+        }
+        let ptr = (REGION_SHA_START as *mut ShaDescriptor).add(*cur_desc);
         *cur_desc += 1;
-        ptr
+        Some(ptr)
     }
 }
 
+// Returns the descriptor following the last one allocated, which is
+// always within the SHA region.
+fn end_desc() -> *mut ShaDescriptor {
+    // SAFETY: Single threaded, and alloc_desc leaves room for this.
+    unsafe { (REGION_SHA_START as *mut ShaDescriptor).add(*CUR_DESC.0.get()) }
+}
+
 // Computes a raw digest of the given slice.  The data must already
 // contain the end marker and the trailer.
-fn raw_digest(data: &[u32]) -> &'static Digest {
-    assert_eq!(data.len() % CHUNK_SIZE, 0);
-    // Allocate fresh memory that's guaranteed to be uninitialized so
-    // the host can write to it.
+//
+// The circuit checks every SHA request against the contents of memory
+// once execution has finished, so neither the data nor the memory the
+// host writes the digest into may change afterwards.  The host writes
+// into a fresh slot which is kept for the rest of the execution, and
+// the caller gets a copy.
+fn raw_digest(data: &[u32]) -> Digest {
+    let slot = Box::leak(Box::new(mem::MaybeUninit::<Digest>::uninit())).as_mut_ptr();
+    // SAFETY: The slot has never been written to by the guest.
     unsafe {
-        let alloced = Box::<mem::MaybeUninit<Digest>>::new(mem::MaybeUninit::<Digest>::uninit());
-        let digest = (*Box::into_raw(alloced)).as_mut_ptr();
-        raw_digest_to(data, digest);
-        &*digest
+        raw_digest_to(data, slot);
+        slot.read_volatile()
     }
 }
 
+// Computes a raw digest of the words in the given buffer, which must
+// already contain the end marker and the trailer.  The buffer is kept
+// for the rest of the execution; see raw_digest.
+fn raw_digest_buf(buf: Vec<u32>) -> Digest {
+    raw_digest(buf.leak())
+}
+
 // Computes a raw digest of the given slice, and stores the digest in
 // the given pointer.  The digest memory must be uninitilaized.
+//
+// Once every descriptor has been used, the digest is computed in
+// software instead, which takes far more cycles but lets the execution
+// carry on.
+#[cfg(target_arch = "riscv32")]
 pub(crate) unsafe fn raw_digest_to(data: &[u32], digest: *mut Digest) {
     assert_eq!(data.len() % CHUNK_SIZE, 0);
     let type_count = data.len() / CHUNK_SIZE;
 
-    let desc_ptr = alloc_desc();
+    let desc_ptr = match alloc_desc() {
+        Some(desc_ptr) => desc_ptr,
+        None => return software_digest_to(data, digest),
+    };
 
     let ptr = data.as_ptr();
     crate::memory_barrier(ptr);
-    desc_ptr.write_volatile(SHADescriptor {
-        type_count,
+    desc_ptr.write_volatile(ShaDescriptor {
+        type_count: type_count as u32,
         idx: 0,
-        source: ptr as usize,
-        digest: digest as usize,
+        source: ptr as u32,
+        digest: digest as u32,
     });
 
     GPIO_SHA.write_volatile(desc_ptr);
 }
 
+// Computes a raw digest of the given slice in software, and stores the
+// digest in the given pointer.
+#[cfg(not(target_arch = "riscv32"))]
+pub(crate) unsafe fn raw_digest_to(data: &[u32], digest: *mut Digest) {
+    software_digest_to(data, digest)
+}
+
+// Computes a raw digest of the given slice in software, and stores the
+// digest in the given pointer.
+unsafe fn software_digest_to(data: &[u32], digest: *mut Digest) {
+    assert_eq!(data.len() % CHUNK_SIZE, 0);
+    let mut state = SHA256_INIT;
+    for block in data.chunks_exact(CHUNK_SIZE) {
+        compress(&mut state, block);
+    }
+    digest.write(Digest::new(state));
+}
+
+// The SHA-256 initial state.
+pub(crate) const SHA256_INIT: [u32; DIGEST_WORDS] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+// Compresses a block of words into the given state in software, without
+// using the accelerator.
+pub(crate) fn compress(state: &mut [u32; DIGEST_WORDS], block: &[u32]) {
+    let mut bytes: GenericArray<u8, U64> = GenericArray::default();
+    for (chunk, word) in bytes.chunks_exact_mut(WORD_SIZE).zip(block) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    compress256(state, slice::from_ref(&bytes));
+}
+
 // Calculates the number of words of capacity needed, including end
 // marker and trailer, to take the SHA hash of len_bytes bytes.
 pub(crate) const fn compute_capacity_needed(len_bytes: usize) -> usize {
@@ -126,7 +211,12 @@ pub(crate) fn add_trailer(data: &mut [u32], len_bytes: usize, memtype: MemoryTyp
 }
 
 /// Computes the SHA256 digest of a serialized object.
-pub fn digest<T: Serialize>(val: &T) -> &'static Digest {
+///
+/// The circuit checks every SHA request against the final contents of
+/// memory, so the serialized object and the digest slot stay allocated for
+/// the rest of the execution. Heap use therefore grows with the number of
+/// hashes.
+pub fn digest<T: Serialize>(val: &T) -> Digest {
     // If the object to be serialized is a plain old structure in memory, this
     // should be a good guess for the allocation needed.
     let cap = compute_capacity_needed(mem::size_of_val(val));
@@ -135,13 +225,15 @@ pub fn digest<T: Serialize>(val: &T) -> &'static Digest {
     let len_bytes = buf.len() * WORD_SIZE;
     buf.resize(compute_capacity_needed(len_bytes), 0);
     add_trailer(buf.as_mut_slice(), len_bytes, MemoryType::Normal);
-    raw_digest(buf.as_slice())
+    raw_digest_buf(buf)
 }
 
 /// Makes a digest for a slice of bytes.
 ///
-/// Since there are no guarantees on alignment, an internal copy is made.
-pub fn digest_u8_slice(data: &[u8]) -> &'static Digest {
+/// Since there are no guarantees on alignment, an internal copy is made. As
+/// with [digest], the copy and the digest slot stay allocated for the rest of
+/// the execution; [digest_u8_slice_to] keeps nothing, at a higher cycle cost.
+pub fn digest_u8_slice(data: &[u8]) -> Digest {
     let len_bytes = data.len();
     let cap = compute_capacity_needed(len_bytes);
     let mut data_u32 = Vec::<u32>::with_capacity(cap);
@@ -166,7 +258,155 @@ pub fn digest_u8_slice(data: &[u8]) -> &'static Digest {
     remaining_out[..remaining_in.len()].clone_from_slice(remaining_in);
 
     add_trailer(data_u32.as_mut_slice(), len_bytes, MemoryType::Normal);
-    raw_digest(data_u32.as_slice())
+    raw_digest_buf(data_u32)
+}
+
+/// Computes the SHA-256 digest of a slice of bytes into `out`, in software.
+///
+/// Unlike [digest_u8_slice], this keeps nothing once it returns: the input
+/// and output are the caller's, and no heap is used, so it can be called any
+/// number of times, such as in a Merkle verification loop. It takes about
+/// 5,600 cycles per 64-byte block, as [Sha256] does.
+pub fn digest_u8_slice_to(data: &[u8], out: &mut Digest) {
+    let mut hasher = Sha256::default();
+    Update::update(&mut hasher, data);
+    hasher.finish();
+    *out = Digest::new(hasher.state);
+}
+
+/// Compresses two digests into `out`, in software, as
+/// [hash_pair](risc0_zkp_core::sha::Sha::hash_pair) does: a single block
+/// holding both, with no padding. Like [digest_u8_slice_to], this uses no
+/// heap.
+pub fn hash_pair_to(a: &Digest, b: &Digest, out: &mut Digest) {
+    let mut block = [0; CHUNK_SIZE];
+    block[..DIGEST_WORDS].copy_from_slice(a.get());
+    block[DIGEST_WORDS..].copy_from_slice(b.get());
+    let mut state = SHA256_INIT;
+    compress(&mut state, &block);
+    *out = Digest::new(state);
+}
+
+// Hashes words into `out` in software, as hash_fps does, with the last block
+// padded with zeros.
+fn hash_words_to(words: &[u32], out: &mut Digest) {
+    let mut state = SHA256_INIT;
+    let mut blocks = words.chunks_exact(CHUNK_SIZE);
+    for block in &mut blocks {
+        compress(&mut state, block);
+    }
+    let rest = blocks.remainder();
+    if !rest.is_empty() {
+        let mut block = [0; CHUNK_SIZE];
+        block[..rest.len()].copy_from_slice(rest);
+        compress(&mut state, &block);
+    }
+    *out = Digest::new(state);
+}
+
+/// An incremental SHA-256 hasher.
+///
+/// This implements the RustCrypto [digest](https://docs.rs/digest) traits, so
+/// it can be used wherever a [digest::Digest] is expected. For keyed hashing,
+/// use `hmac::SimpleHmac<Sha256>`, which works with any such digest.
+///
+/// Each block is compressed in software as soon as it is complete, so the
+/// hasher only holds the chaining state and a partial block, and takes no
+/// heap. The accelerator can't be used here: it hashes a whole message from
+/// the SHA-256 initial state, and the message has to stay in memory until the
+/// end of the execution. Software compression takes about 5,600 cycles per
+/// 64-byte block, so prefer [digest_u8_slice] for a message which is already
+/// in memory.
+#[derive(Clone)]
+pub struct Sha256 {
+    state: [u32; DIGEST_WORDS],
+    block: GenericArray<u8, U64>,
+    len_bytes: u64,
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Sha256 {
+            state: SHA256_INIT,
+            block: GenericArray::default(),
+            len_bytes: 0,
+        }
+    }
+}
+
+impl Sha256 {
+    // The number of bytes in the partial block.
+    fn block_len(&self) -> usize {
+        (self.len_bytes % BLOCK_BYTES as u64) as usize
+    }
+
+    // Pads the message and compresses the last blocks, leaving the digest in
+    // the chaining state.
+    fn finish(&mut self) {
+        let len_bits = self.len_bytes * 8;
+        let mut pos = self.block_len();
+        self.block[pos] = END_MARKER;
+        pos += 1;
+        if pos > BLOCK_BYTES - 8 {
+            self.block[pos..].fill(0);
+            compress256(&mut self.state, slice::from_ref(&self.block));
+            pos = 0;
+        }
+        self.block[pos..BLOCK_BYTES - 8].fill(0);
+        self.block[BLOCK_BYTES - 8..].copy_from_slice(&len_bits.to_be_bytes());
+        compress256(&mut self.state, slice::from_ref(&self.block));
+    }
+
+    fn finalize_to(&mut self, out: &mut Output<Self>) {
+        self.finish();
+        for (chunk, word) in out.chunks_exact_mut(WORD_SIZE).zip(self.state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+    }
+}
+
+impl HashMarker for Sha256 {}
+
+impl BlockSizeUser for Sha256 {
+    type BlockSize = U64;
+}
+
+impl OutputSizeUser for Sha256 {
+    type OutputSize = U32;
+}
+
+impl Update for Sha256 {
+    fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let pos = self.block_len();
+            let len = data.len().min(BLOCK_BYTES - pos);
+            self.block[pos..pos + len].copy_from_slice(&data[..len]);
+            self.len_bytes += len as u64;
+            data = &data[len..];
+            if pos + len == BLOCK_BYTES {
+                compress256(&mut self.state, slice::from_ref(&self.block));
+            }
+        }
+    }
+}
+
+impl FixedOutput for Sha256 {
+    fn finalize_into(mut self, out: &mut Output<Self>) {
+        self.finalize_to(out);
+    }
+}
+
+impl Reset for Sha256 {
+    fn reset(&mut self) {
+        *self = Sha256::default();
+    }
+}
+
+impl FixedOutputReset for Sha256 {
+    fn finalize_into_reset(&mut self, out: &mut Output<Self>) {
+        self.finalize_to(out);
+        Reset::reset(self);
+    }
 }
 
 // Set a marker so that the VM knows when the last SHA descriptor is
@@ -175,39 +415,121 @@ pub fn digest_u8_slice(data: &[u8]) -> &'static Digest {
 // the VM looks for.
 pub(crate) fn finalize() {
     unsafe {
-        let ptr = alloc_desc();
-        let type_field_ptr: *mut usize = core::ptr::addr_of_mut!((*ptr).type_count);
+        let ptr = end_desc();
+        let type_field_ptr: *mut u32 = core::ptr::addr_of_mut!((*ptr).type_count);
         type_field_ptr.write_volatile(0);
     }
 }
 
-/// A guest-side [Sha] implementation.
+/// A [Digest] owned by value, as returned by [Impl] and [AcceleratedImpl].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct OwnedDigest(Digest);
+
+impl Deref for OwnedDigest {
+    type Target = Digest;
+
+    fn deref(&self) -> &Digest {
+        &self.0
+    }
+}
+
+impl DerefMut for OwnedDigest {
+    fn deref_mut(&mut self) -> &mut Digest {
+        &mut self.0
+    }
+}
+
+impl From<Digest> for OwnedDigest {
+    fn from(digest: Digest) -> Self {
+        OwnedDigest(digest)
+    }
+}
+
+/// A guest-side [Sha] implementation which runs in bounded memory.
+///
+/// Digests are returned by value, and each hash is computed in software from
+/// the caller's data, as [digest_u8_slice_to] and [hash_pair_to] do, so
+/// nothing is kept on the heap and hash-heavy code such as Merkle
+/// verification can run for as long as the cycle limit allows. Use
+/// [AcceleratedImpl] where the cycles matter more than the memory.
 ///
 /// [Sha]: risc0_zkp_core::sha::Sha
 #[derive(Debug, Clone)]
 pub struct Impl {}
 
 impl risc0_zkp_core::sha::Sha for Impl {
-    type DigestPtr = &'static Digest;
+    type DigestPtr = OwnedDigest;
 
     fn hash_bytes(&self, bytes: &[u8]) -> Self::DigestPtr {
-        digest_u8_slice(bytes)
+        let mut out = OwnedDigest::default();
+        digest_u8_slice_to(bytes, &mut out);
+        out
     }
 
     fn hash_pair(&self, a: &Digest, b: &Digest) -> Self::DigestPtr {
-        raw_digest(bytemuck::cast_slice(&[*a, *b]))
+        let mut out = OwnedDigest::default();
+        hash_pair_to(a, b, &mut out);
+        out
+    }
+
+    fn hash_fps(&self, fps: &[Fp]) -> Self::DigestPtr {
+        // Fps do not not include standard sha header.
+        let mut out = OwnedDigest::default();
+        hash_words_to(bytemuck::cast_slice(fps), &mut out);
+        out
+    }
+
+    fn hash_fp4s(&self, fp4s: &[Fp4]) -> Self::DigestPtr {
+        self.hash_fps(bytemuck::cast_slice(fp4s))
+    }
+
+    // Generate a new digest by mixing two digests together via XOR,
+    // and storing into the first digest.
+    fn mix(&self, pool: &mut Self::DigestPtr, val: &Digest) {
+        for (word, val) in pool.get_mut().iter_mut().zip(val.get()) {
+            *word ^= val;
+        }
+    }
+}
+
+/// A guest-side [Sha] implementation which uses the SHA accelerator.
+///
+/// Every hash keeps its input and an 8-word digest slot on the heap until the
+/// end of the execution, as [digest] does, so heap use grows with the number
+/// of hashes. In exchange, a block takes a small fraction of the cycles it
+/// takes [Impl].
+///
+/// [Sha]: risc0_zkp_core::sha::Sha
+#[derive(Debug, Clone)]
+pub struct AcceleratedImpl {}
+
+impl risc0_zkp_core::sha::Sha for AcceleratedImpl {
+    type DigestPtr = OwnedDigest;
+
+    fn hash_bytes(&self, bytes: &[u8]) -> Self::DigestPtr {
+        digest_u8_slice(bytes).into()
+    }
+
+    fn hash_pair(&self, a: &Digest, b: &Digest) -> Self::DigestPtr {
+        // The inputs are copied into a buffer which is kept, since the
+        // caller is free to reuse them, and the circuit checks the request
+        // against the final contents of memory.
+        let mut buf = Vec::with_capacity(2 * DIGEST_WORDS);
+        buf.extend_from_slice(a.get());
+        buf.extend_from_slice(b.get());
+        raw_digest_buf(buf).into()
     }
 
     fn hash_fps(&self, fps: &[Fp]) -> Self::DigestPtr {
         // Fps do not not include standard sha header.
         if fps.len() % CHUNK_SIZE == 0 {
-            raw_digest(bytemuck::cast_slice(fps))
+            raw_digest(bytemuck::cast_slice(fps)).into()
         } else {
             let size = align_up(fps.len(), CHUNK_SIZE);
             let mut buf: Vec<u32> = Vec::with_capacity(size);
             buf.extend(bytemuck::cast_slice(fps));
             buf.resize(size, 0);
-            raw_digest(&buf)
+            raw_digest_buf(buf).into()
         }
     }
 
@@ -218,13 +540,34 @@ impl risc0_zkp_core::sha::Sha for Impl {
     // Generate a new digest by mixing two digests together via XOR,
     // and storing into the first digest.
     fn mix(&self, pool: &mut Self::DigestPtr, val: &Digest) {
-        let mut digest = Box::<Digest>::new(Digest::default());
-        for i in 0..DIGEST_WORDS {
-            digest.get_mut()[i] = pool.get()[i] ^ val.get()[i];
+        for (word, val) in pool.get_mut().iter_mut().zip(val.get()) {
+            *word ^= val;
         }
-        unsafe {
-            let ptr: *const Digest = &*digest;
-            *pool = &*ptr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use digest::Digest as _;
+    use std::vec::Vec;
+
+    use super::Sha256;
+
+    #[test]
+    fn sha256() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 7 + 3) as u8).collect();
+        for len in [0, 1, 55, 56, 63, 64, 65, 119, 120, 128, 200, 1000] {
+            let expected = sha2::Sha256::digest(&data[..len]);
+            assert_eq!(Sha256::digest(&data[..len]), expected);
+
+            // Feeding the data in uneven pieces gives the same digest.
+            let mut hasher = Sha256::new();
+            for chunk in data[..len].chunks(13) {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.finalize_reset(), expected);
+            hasher.update(&data[..len]);
+            assert_eq!(hasher.finalize(), expected);
         }
     }
 }
@@ -0,0 +1,691 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A signature is 64 bytes, the big-endian scalars `r` and `s`, and a public
+//! key is a SEC1 encoded point, either uncompressed (65 bytes) or compressed
+//! (33 bytes).
+//!
+//! # Example
+//!
+//! ```
+//! use risc0_zkvm_guest::sig::{ecdsa, Error};
+//!
+//! fn check(public_key: &[u8], msg: &[u8], sig: &[u8]) -> Result<(), Error> {
+//!     ecdsa::verify(&ecdsa::Curve::secp256k1(), public_key, msg, sig)
+//! }
+//! ```
+
+use _alloc::vec::Vec;
+
+use super::{wnaf, Error, Pow, Reader, Words, INVERT, TABLES, TABLE_LEN};
+use crate::{
+    bigint::{Montgomery, U256},
+    sha,
+};
+
+/// A curve `y^2 = x^3 + ax + b` over a 256-bit prime field, whose points
+/// form a group of prime order generated by a base point.
+///
+/// The field prime must be 3 mod 4, so square roots are a single power.
+#[derive(Clone, Debug)]
+pub struct Curve {
+    // Arithmetic modulo the field prime.
+    field: Montgomery<8>,
+    // Arithmetic modulo the group order.
+    scalars: Montgomery<8>,
+    // The coefficients and the base point, in Montgomery form.
+    a: U256,
+    b: U256,
+    g: Point,
+    // (p + 1) / 4, the exponent which gives a square root.
+    sqrt_exp: U256,
+}
+
+// A point in Jacobian coordinates, in Montgomery form, which is
+// (x / z^2, y / z^3) in affine coordinates. Any point with z = 0 is the
+// identity.
+#[derive(Clone, Copy, Debug)]
+struct Point {
+    x: U256,
+    y: U256,
+    z: U256,
+}
+
+impl Point {
+    const IDENTITY: Point = Point {
+        x: U256::ZERO,
+        y: U256::ZERO,
+        z: U256::ZERO,
+    };
+
+    fn is_identity(&self) -> bool {
+        self.z.is_zero()
+    }
+
+    fn save(&self, words: &mut Words) {
+        words.uint(&self.x);
+        words.uint(&self.y);
+        words.uint(&self.z);
+    }
+
+    fn load(words: &mut Reader) -> Option<Self> {
+        let (x, y, z) = (words.uint()?, words.uint()?, words.uint()?);
+        Some(Point { x, y, z })
+    }
+}
+
+impl Curve {
+    // Builds a curve from the big-endian hex of its parameters.
+    fn new(p: &str, a: &str, b: &str, gx: &str, gy: &str, n: &str) -> Self {
+        let p = U256::from_be_hex(p);
+        let field = Montgomery::new(&p);
+        let scalars = Montgomery::new(&U256::from_be_hex(n));
+        let to_field = |hex| field.to_montgomery(&U256::from_be_hex(hex));
+        let g = Point {
+            x: to_field(gx),
+            y: to_field(gy),
+            z: field.one(),
+        };
+        Curve {
+            a: to_field(a),
+            b: to_field(b),
+            g,
+            sqrt_exp: p.overflowing_add(&U256::ONE).0.shr(2),
+            field,
+            scalars,
+        }
+    }
+
+    /// The secp256k1 curve, from SEC 2.
+    pub fn secp256k1() -> Self {
+        Curve::new(
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "0000000000000000000000000000000000000000000000000000000000000007",
+            "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            "483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8",
+            "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141",
+        )
+    }
+
+    /// The P-256 curve, also known as secp256r1, from FIPS 186-4.
+    pub fn p256() -> Self {
+        Curve::new(
+            "ffffffff00000001000000000000000000000000ffffffffffffffffffffffff",
+            "ffffffff00000001000000000000000000000000fffffffffffffffffffffffc",
+            "5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b",
+            "6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296",
+            "4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5",
+            "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551",
+        )
+    }
+
+    // Returns x^3 + ax + b.
+    fn rhs(&self, x: &U256) -> U256 {
+        let f = &self.field;
+        let x3 = f.mul(&f.mul(x, x), x);
+        f.add(&f.add(&x3, &f.mul(&self.a, x)), &self.b)
+    }
+
+    // Returns 2p, with the dbl-2007-bl formulas.
+    fn double(&self, p: &Point) -> Point {
+        let f = &self.field;
+        let xx = f.mul(&p.x, &p.x);
+        let yy = f.mul(&p.y, &p.y);
+        let yyyy = f.mul(&yy, &yy);
+        let zz = f.mul(&p.z, &p.z);
+        let t = f.add(&p.x, &yy);
+        let s = f.sub(&f.sub(&f.mul(&t, &t), &xx), &yyyy);
+        let s = f.add(&s, &s);
+        let mut m = f.add(&f.add(&xx, &xx), &xx);
+        if !self.a.is_zero() {
+            m = f.add(&m, &f.mul(&self.a, &f.mul(&zz, &zz)));
+        }
+        let x = f.sub(&f.mul(&m, &m), &f.add(&s, &s));
+        let yyyy2 = f.add(&yyyy, &yyyy);
+        let yyyy4 = f.add(&yyyy2, &yyyy2);
+        let yyyy8 = f.add(&yyyy4, &yyyy4);
+        let y = f.sub(&f.mul(&m, &f.sub(&s, &x)), &yyyy8);
+        let t = f.add(&p.y, &p.z);
+        let z = f.sub(&f.sub(&f.mul(&t, &t), &yy), &zz);
+        Point { x, y, z }
+    }
+
+    // Returns p + q, with the add-2007-bl formulas.
+    fn add(&self, p: &Point, q: &Point) -> Point {
+        if p.is_identity() {
+            return *q;
+        }
+        if q.is_identity() {
+            return *p;
+        }
+        let f = &self.field;
+        let z1z1 = f.mul(&p.z, &p.z);
+        let z2z2 = f.mul(&q.z, &q.z);
+        let u1 = f.mul(&p.x, &z2z2);
+        let u2 = f.mul(&q.x, &z1z1);
+        let s1 = f.mul(&f.mul(&p.y, &q.z), &z2z2);
+        let s2 = f.mul(&f.mul(&q.y, &p.z), &z1z1);
+        let h = f.sub(&u2, &u1);
+        let r = f.sub(&s2, &s1);
+        if h.is_zero() {
+            // The points have the same x, so they are equal or opposite.
+            return match r.is_zero() {
+                true => self.double(p),
+                false => Point::IDENTITY,
+            };
+        }
+        let r = f.add(&r, &r);
+        let h2 = f.add(&h, &h);
+        let i = f.mul(&h2, &h2);
+        let j = f.mul(&h, &i);
+        let v = f.mul(&u1, &i);
+        let x = f.sub(&f.sub(&f.mul(&r, &r), &j), &f.add(&v, &v));
+        let s1j = f.mul(&s1, &j);
+        let y = f.sub(&f.mul(&r, &f.sub(&v, &x)), &f.add(&s1j, &s1j));
+        let t = f.add(&p.z, &q.z);
+        let z = f.mul(&f.sub(&f.sub(&f.mul(&t, &t), &z1z1), &z2z2), &h);
+        Point { x, y, z }
+    }
+
+    // Returns -p.
+    fn neg(&self, p: &Point) -> Point {
+        Point {
+            y: self.field.sub(&U256::ZERO, &p.y),
+            ..*p
+        }
+    }
+
+    // Returns acc plus the multiple of a point which a NAF digit selects from
+    // the point's table.
+    fn add_digit(&self, acc: &Point, table: &[Point; TABLE_LEN], digit: i8) -> Point {
+        match digit {
+            0 => *acc,
+            1.. => self.add(acc, &table[digit as usize / 2]),
+            _ => self.add(acc, &self.neg(&table[digit.unsigned_abs() as usize / 2])),
+        }
+    }
+}
+
+// The costs of the pieces of a verification, in field multiplications.
+const DOUBLE: u32 = 10;
+const ADD: u32 = 16;
+
+// The NAFs of u1 and u2, which are cheaper to compute again in each
+// execution than to save.
+type Digits = ([i8; 257], [i8; 257]);
+
+// The piece of a verification which a verifier does next.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Step {
+    // Applying the next bit of the exponent which gives y for a compressed
+    // public key, or checking y once the exponent is done.
+    Decompress,
+    // Computing u1 and u2 from the signature.
+    Scalars,
+    // Computing an entry of the tables of odd multiples, of g for the first
+    // TABLE_LEN and then of the public key.
+    Table(usize),
+    // Doubling the accumulator and adding the digits at the index below
+    // this one.
+    Digit(usize),
+    // Checking the accumulated point against r.
+    Finish,
+}
+
+/// An ECDSA verification which is carried out a piece at a time, so that it
+/// can be split across executions, as described in the
+/// [module documentation](super).
+#[derive(Clone, Debug)]
+pub struct Verifier {
+    step: Step,
+    // The public key. A compressed key's y is the square root `sqrt` computes,
+    // negated if its parity doesn't match `odd`.
+    q: Point,
+    odd: bool,
+    sqrt: Pow,
+    // The digest, reduced modulo the group order, and the signature.
+    z: U256,
+    r: U256,
+    s: U256,
+    // acc accumulates u1 * g + u2 * q, using the tables of odd multiples of
+    // g and q.
+    u1: U256,
+    u2: U256,
+    tables: [[Point; TABLE_LEN]; 2],
+    acc: Point,
+}
+
+impl Verifier {
+    /// Starts verifying a signature over a message digest, after checking
+    /// that the public key and the signature are well formed.
+    ///
+    /// A compressed public key whose x has no point on the curve is only
+    /// found to be invalid by [Verifier::run].
+    pub fn new(
+        curve: &Curve,
+        public_key: &[u8],
+        digest: &[u8; 32],
+        sig: &[u8],
+    ) -> Result<Self, Error> {
+        let f = &curve.field;
+        let to_field = |bytes| {
+            let value = U256::from_be_slice(bytes);
+            match value < *f.modulus() {
+                true => Ok(f.to_montgomery(&value)),
+                false => Err(Error::InvalidPublicKey),
+            }
+        };
+        let (x, y) = match (public_key.len(), public_key.first()) {
+            (65, Some(4)) => (
+                to_field(&public_key[1..33])?,
+                Some(to_field(&public_key[33..])?),
+            ),
+            (33, Some(2 | 3)) => (to_field(&public_key[1..])?, None),
+            _ => return Err(Error::InvalidPublicKey),
+        };
+        let rhs = curve.rhs(&x);
+        let step = match y {
+            Some(y) if f.mul(&y, &y) != rhs => return Err(Error::InvalidPublicKey),
+            Some(_) => Step::Scalars,
+            None => Step::Decompress,
+        };
+
+        if sig.len() != 64 {
+            return Err(Error::InvalidSignature);
+        }
+        let scalars = &curve.scalars;
+        let n = scalars.modulus();
+        let r = U256::from_be_slice(&sig[..32]);
+        let s = U256::from_be_slice(&sig[32..]);
+        if r.is_zero() || s.is_zero() || r >= *n || s >= *n {
+            return Err(Error::InvalidSignature);
+        }
+
+        Ok(Verifier {
+            step,
+            q: Point {
+                x,
+                y: y.unwrap_or_default(),
+                z: f.one(),
+            },
+            odd: public_key[0] == 3,
+            sqrt: Pow::new(f, &rhs, &curve.sqrt_exp),
+            z: scalars.reduce(&U256::from_be_slice(digest)),
+            r,
+            s,
+            u1: U256::ZERO,
+            u2: U256::ZERO,
+            tables: [[Point::IDENTITY; TABLE_LEN]; 2],
+            acc: Point::IDENTITY,
+        })
+    }
+
+    /// Does pieces of the verification until the next one would take the
+    /// field multiplications done past `budget`, and returns the result if
+    /// that finishes the verification.
+    ///
+    /// At least one piece is done, whatever the budget, so that every call
+    /// makes progress. The costliest piece takes about 70 multiplications.
+    pub fn run(&mut self, curve: &Curve, budget: u32) -> Option<Result<(), Error>> {
+        let mut digits = None;
+        let mut spent = 0;
+        loop {
+            let cost = match self.step {
+                Step::Decompress if self.sqrt.is_done() => 2,
+                Step::Decompress => self.sqrt.cost(&curve.sqrt_exp),
+                Step::Scalars => INVERT + 3,
+                Step::Table(i) if i % TABLE_LEN == 0 => 0,
+                Step::Table(_) => DOUBLE + ADD,
+                Step::Digit(i) => {
+                    let (d1, d2) = self.digits(&mut digits);
+                    DOUBLE + ADD * ((d1[i - 1] != 0) as u32 + (d2[i - 1] != 0) as u32)
+                }
+                Step::Finish => INVERT + 5,
+            };
+            if spent > 0 && spent + cost > budget {
+                return None;
+            }
+            spent += cost;
+            if let Some(result) = self.advance(curve, &mut digits) {
+                return Some(result);
+            }
+        }
+    }
+
+    // Does the next piece of the verification, and returns the result if it
+    // was the last.
+    fn advance(&mut self, curve: &Curve, digits: &mut Option<Digits>) -> Option<Result<(), Error>> {
+        let f = &curve.field;
+        match self.step {
+            Step::Decompress if self.sqrt.is_done() => {
+                let y = self.sqrt.acc;
+                if f.mul(&y, &y) != self.sqrt.base {
+                    return Some(Err(Error::InvalidPublicKey));
+                }
+                self.q.y = match f.from_montgomery(&y).bit(0) == self.odd {
+                    true => y,
+                    false => f.sub(&U256::ZERO, &y),
+                };
+                self.step = Step::Scalars;
+            }
+            Step::Decompress => self.sqrt.step(f, &curve.sqrt_exp),
+            Step::Scalars => {
+                // Multiplying a value by one in Montgomery form gives a value
+                // which isn't, so u1 and u2 come out as ordinary integers.
+                let scalars = &curve.scalars;
+                let w = scalars.invert(&scalars.to_montgomery(&self.s));
+                self.u1 = scalars.mul(&self.z, &w);
+                self.u2 = scalars.mul(&self.r, &w);
+                self.step = Step::Table(0);
+            }
+            Step::Table(i) => {
+                // Each entry is the one before plus 2p, which is cheaper to
+                // compute again for each entry than to save.
+                let (t, j) = (i / TABLE_LEN, i % TABLE_LEN);
+                let p = if t == 0 { curve.g } else { self.q };
+                self.tables[t][j] = match j {
+                    0 => p,
+                    _ => curve.add(&self.tables[t][j - 1], &curve.double(&p)),
+                };
+                self.step = match i + 1 {
+                    TABLES => self.first_digit(digits),
+                    next => Step::Table(next),
+                };
+            }
+            Step::Digit(i) => {
+                let (d1, d2) = self.digits(digits);
+                self.acc = curve.double(&self.acc);
+                self.acc = curve.add_digit(&self.acc, &self.tables[0], d1[i - 1]);
+                self.acc = curve.add_digit(&self.acc, &self.tables[1], d2[i - 1]);
+                self.step = match i {
+                    1 => Step::Finish,
+                    _ => Step::Digit(i - 1),
+                };
+            }
+            Step::Finish => {
+                if self.acc.is_identity() {
+                    return Some(Err(Error::Mismatch));
+                }
+                let z_inv = f.invert(&self.acc.z);
+                let x = f.from_montgomery(&f.mul(&self.acc.x, &f.mul(&z_inv, &z_inv)));
+                return Some(match curve.scalars.reduce(&x) == self.r {
+                    true => Ok(()),
+                    false => Err(Error::Mismatch),
+                });
+            }
+        }
+        None
+    }
+
+    // Returns the step for the top digit, as doublings of the identity can
+    // be skipped.
+    fn first_digit(&self, digits: &mut Option<Digits>) -> Step {
+        let (d1, d2) = self.digits(digits);
+        let top = (0..d1.len())
+            .rev()
+            .find(|&i| d1[i] != 0 || d2[i] != 0)
+            .map_or(0, |i| i + 1);
+        match top {
+            0 => Step::Finish,
+            _ => Step::Digit(top),
+        }
+    }
+
+    fn digits<'a>(&self, digits: &'a mut Option<Digits>) -> &'a Digits {
+        digits.get_or_insert_with(|| (wnaf(&self.u1), wnaf(&self.u2)))
+    }
+
+    /// Saves the progress of the verification, for [Verifier::from_words] to
+    /// pick up.
+    pub fn to_words(&self) -> Vec<u32> {
+        let (tag, arg) = match self.step {
+            Step::Decompress => (0, 0),
+            Step::Scalars => (1, 0),
+            Step::Table(i) => (2, i),
+            Step::Digit(i) => (3, i),
+            Step::Finish => (4, 0),
+        };
+        let mut words = Words::default();
+        words.u32(tag);
+        words.u32(arg as u32);
+        words.u32(self.odd as u32);
+        self.q.save(&mut words);
+        self.sqrt.save(&mut words);
+        for value in [&self.z, &self.r, &self.s, &self.u1, &self.u2] {
+            words.uint(value);
+        }
+        for point in self.tables.iter().flatten().chain([&self.acc]) {
+            point.save(&mut words);
+        }
+        words.0
+    }
+
+    /// Picks up a verification from the words [Verifier::to_words] saved, or
+    /// returns `None` if they are malformed.
+    pub fn from_words(words: &[u32]) -> Option<Self> {
+        let mut words = Reader(words);
+        let arg = (words.u32()?, words.u32()? as usize);
+        let step = match arg {
+            (0, 0) => Step::Decompress,
+            (1, 0) => Step::Scalars,
+            (2, i) if i < TABLES => Step::Table(i),
+            (3, i @ 1..=257) => Step::Digit(i),
+            (4, 0) => Step::Finish,
+            _ => return None,
+        };
+        let odd = match words.u32()? {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+        let q = Point::load(&mut words)?;
+        let sqrt = Pow::load(&mut words)?;
+        let (z, r, s) = (words.uint()?, words.uint()?, words.uint()?);
+        let (u1, u2) = (words.uint()?, words.uint()?);
+        let mut tables = [[Point::IDENTITY; TABLE_LEN]; 2];
+        for point in tables.iter_mut().flatten() {
+            *point = Point::load(&mut words)?;
+        }
+        let acc = Point::load(&mut words)?;
+        words.finish(Verifier {
+            step,
+            q,
+            odd,
+            sqrt,
+            z,
+            r,
+            s,
+            u1,
+            u2,
+            tables,
+            acc,
+        })
+    }
+}
+
+/// Returns the SHA-256 digest of `msg`, computed with the SHA accelerator, as
+/// the bytes [verify_prehash] and [Verifier::new] take.
+pub fn digest(msg: &[u8]) -> [u8; 32] {
+    let digest = sha::digest_u8_slice(msg);
+    let mut bytes = [0; 32];
+    for (chunk, word) in bytes.chunks_exact_mut(4).zip(digest.as_slice()) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    bytes
+}
+
+/// Verifies a signature over the SHA-256 digest of `msg`, which is computed
+/// with the SHA accelerator.
+pub fn verify(curve: &Curve, public_key: &[u8], msg: &[u8], sig: &[u8]) -> Result<(), Error> {
+    verify_prehash(curve, public_key, &digest(msg), sig)
+}
+
+/// Verifies a signature over a message digest which has already been
+/// computed.
+pub fn verify_prehash(
+    curve: &Curve,
+    public_key: &[u8],
+    digest: &[u8; 32],
+    sig: &[u8],
+) -> Result<(), Error> {
+    let mut verifier = Verifier::new(curve, public_key, digest, sig)?;
+    loop {
+        if let Some(result) = verifier.run(curve, u32::MAX) {
+            return result;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{digest, verify, verify_prehash, Curve, Verifier};
+    use crate::sig::{from_hex, Error};
+
+    const MSG: &[u8] = b"hello world";
+
+    // Signatures of MSG made with the Python cryptography package, for the
+    // private key 0x1234567890abcdef1234567890abcdef.
+    const SECP256K1_KEY: &str =
+        "049377c312145a5afb911bf9e8c067bcf6094c533603687850df502b61290bbf5e\
+                                 742ba607d6ae1fc82dcccb176e7c8f9bdc7bb61e3ab527cecaf6144b679779fb";
+    const SECP256K1_SIG: &str = "c0a3ece0086ba5db73e7ea9d0127675f58736e1197703209596f6ea4128c585f\
+                                 68257e27fbe79ec3bf12b9413771a84b239f05c52047bff7af455120d238db0d";
+    const P256_KEY: &str = "04f9ebe464147cc102d92324d099d927c1a50e42d57a08a116ffec0a29819d8c65\
+                            70baf7d12888902a6a0c1d67add103123c859ab69bf8e9431c9384930d4b7eee";
+    const P256_SIG: &str = "085aa28d4dd369a98276cf99f2c5096c7c304fd05312ab0307eea6592feca811\
+                            daeba9db138411e33e4f5eb3f8d84e25f48ef88c70fa6ca61c77ad958b46da14";
+
+    fn check(curve: &Curve, key: &str, sig: &str) {
+        let key = from_hex(key);
+        let sig = from_hex(sig);
+        assert_eq!(verify(curve, &key, MSG, &sig), Ok(()));
+
+        // The compressed form of the key.
+        let mut compressed = key[..33].to_vec();
+        compressed[0] = 2 | (key[64] & 1);
+        assert_eq!(verify(curve, &compressed, MSG, &sig), Ok(()));
+        compressed[0] ^= 1;
+        assert_eq!(verify(curve, &compressed, MSG, &sig), Err(Error::Mismatch));
+
+        assert_eq!(verify(curve, &key, b"hello", &sig), Err(Error::Mismatch));
+        assert_eq!(
+            verify_prehash(curve, &key, &[0; 32], &sig),
+            Err(Error::Mismatch)
+        );
+
+        let mut bad = sig.clone();
+        bad[0..32].fill(0);
+        assert_eq!(verify(curve, &key, MSG, &bad), Err(Error::InvalidSignature));
+        bad[0..32].fill(0xff);
+        assert_eq!(verify(curve, &key, MSG, &bad), Err(Error::InvalidSignature));
+        assert_eq!(
+            verify(curve, &key, MSG, &sig[..63]),
+            Err(Error::InvalidSignature)
+        );
+
+        let mut bad = key.clone();
+        bad[64] ^= 1;
+        assert_eq!(verify(curve, &bad, MSG, &sig), Err(Error::InvalidPublicKey));
+        assert_eq!(
+            verify(curve, &key[..64], MSG, &sig),
+            Err(Error::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn secp256k1() {
+        let curve = Curve::secp256k1();
+        check(&curve, SECP256K1_KEY, SECP256K1_SIG);
+
+        // There is no point with x = 5 on secp256k1.
+        let mut key = [0; 33];
+        key[0] = 2;
+        key[32] = 5;
+        let sig = from_hex(SECP256K1_SIG);
+        assert_eq!(
+            verify(&curve, &key, MSG, &sig),
+            Err(Error::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn p256() {
+        check(&Curve::p256(), P256_KEY, P256_SIG);
+    }
+
+    // Verifies a signature with a verifier which is saved and picked up
+    // again after every budget's worth of pieces, returning the result and
+    // the number of runs it took.
+    fn split(curve: &Curve, key: &[u8], msg: &[u8], sig: &[u8]) -> (Result<(), Error>, usize) {
+        let mut words = match Verifier::new(curve, key, &digest(msg), sig) {
+            Ok(verifier) => verifier.to_words(),
+            Err(err) => return (Err(err), 0),
+        };
+        for runs in 1.. {
+            let mut verifier = Verifier::from_words(&words).unwrap();
+            if let Some(result) = verifier.run(curve, 150) {
+                return (result, runs);
+            }
+            words = verifier.to_words();
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn split_verification() {
+        for (curve, key, sig) in [
+            (Curve::secp256k1(), SECP256K1_KEY, SECP256K1_SIG),
+            (Curve::p256(), P256_KEY, P256_SIG),
+        ] {
+            let (key, sig) = (from_hex(key), from_hex(sig));
+            let (result, runs) = split(&curve, &key, MSG, &sig);
+            assert_eq!(result, Ok(()));
+            assert!(runs > 10);
+            assert_eq!(split(&curve, &key, b"hello", &sig).0, Err(Error::Mismatch));
+
+            // A compressed key takes a square root, a bit at a time.
+            let mut compressed = key[..33].to_vec();
+            compressed[0] = 2 | (key[64] & 1);
+            let (result, compressed_runs) = split(&curve, &compressed, MSG, &sig);
+            assert_eq!(result, Ok(()));
+            assert!(compressed_runs > runs);
+        }
+
+        // There is no point with x = 5 on secp256k1, which is found once the
+        // square root is done.
+        let curve = Curve::secp256k1();
+        let mut key = [0; 33];
+        key[0] = 2;
+        key[32] = 5;
+        let sig = from_hex(SECP256K1_SIG);
+        assert_eq!(
+            split(&curve, &key, MSG, &sig).0,
+            Err(Error::InvalidPublicKey)
+        );
+        assert_eq!(
+            split(&curve, &key, MSG, &sig[..63]).0,
+            Err(Error::InvalidSignature)
+        );
+
+        let words = Verifier::new(&curve, &from_hex(SECP256K1_KEY), &digest(MSG), &sig)
+            .unwrap()
+            .to_words();
+        assert!(Verifier::from_words(&words[..words.len() - 1]).is_none());
+        assert!(Verifier::from_words(&[words.as_slice(), &[0]].concat()).is_none());
+        let mut bad = words.clone();
+        bad[0] = 5;
+        assert!(Verifier::from_words(&bad).is_none());
+    }
+}
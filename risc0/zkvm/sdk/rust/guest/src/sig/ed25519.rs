@@ -0,0 +1,592 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A signature is 64 bytes, the encoded point `R` followed by the
+//! little-endian scalar `S`, and a public key is a 32-byte encoded point.
+//!
+//! Verification checks that `[S]B - [k]A` encodes to `R`, where `k` is the
+//! SHA-512 hash of `R`, the public key `A` and the message, reduced modulo the
+//! group order. Encodings of points and `S` must be canonical.
+//!
+//! # Example
+//!
+//! ```
+//! use risc0_zkvm_guest::sig::{ed25519, Error};
+//!
+//! fn check(public_key: &[u8], msg: &[u8], sig: &[u8]) -> Result<(), Error> {
+//!     ed25519::verify(public_key, msg, sig)
+//! }
+//! ```
+
+use _alloc::vec::Vec;
+
+use sha2::{Digest as _, Sha512};
+
+use super::{wnaf, Error, Pow, Reader, Words, INVERT, TABLES, TABLE_LEN};
+use crate::bigint::{Montgomery, U256};
+
+// The field prime, 2^255 - 19.
+const P: &str = "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffed";
+// The order of the base point, 2^252 + 27742317777372353535851937790883648493.
+const L: &str = "1000000000000000000000000000000014def9dea2f79cd65812631a5cf5d3ed";
+// The curve constant d = -121665 / 121666.
+const D: &str = "52036cee2b6ffe738cc740797779e89800700a4d4141d8ab75eb4dca135978a3";
+// A square root of -1.
+const SQRT_M1: &str = "2b8324804fc1df0b2b4d00993dfbd7a72f431806ad2fe478c4ee1b274a0ea0b0";
+// The base point.
+const BX: &str = "216936d3cd6e53fec0a4e231fdd6dc5c692cc7609525a7b2c9562d608f25d51a";
+const BY: &str = "6666666666666666666666666666666666666666666666666666666666666658";
+
+// A point in extended coordinates, in Montgomery form, which is
+// (x / z, y / z) in affine coordinates, with x * y = t * z.
+#[derive(Clone, Copy, Debug)]
+struct Point {
+    x: U256,
+    y: U256,
+    z: U256,
+    t: U256,
+}
+
+impl Point {
+    fn save(&self, words: &mut Words) {
+        for value in [&self.x, &self.y, &self.z, &self.t] {
+            words.uint(value);
+        }
+    }
+
+    fn load(words: &mut Reader) -> Option<Self> {
+        let (x, y) = (words.uint()?, words.uint()?);
+        let (z, t) = (words.uint()?, words.uint()?);
+        Some(Point { x, y, z, t })
+    }
+}
+
+// The curve -x^2 + y^2 = 1 + d x^2 y^2 over the field of P.
+struct Curve {
+    field: Montgomery<8>,
+    scalars: Montgomery<8>,
+    // d and 2d, in Montgomery form.
+    d: U256,
+    d2: U256,
+    sqrt_m1: U256,
+    b: Point,
+    identity: Point,
+    // (p - 5) / 8, the exponent used to take square roots.
+    sqrt_exp: U256,
+}
+
+impl Curve {
+    fn new() -> Self {
+        let p = U256::from_be_hex(P);
+        let field = Montgomery::new(&p);
+        let to_field = |hex| field.to_montgomery(&U256::from_be_hex(hex));
+        let (x, y) = (to_field(BX), to_field(BY));
+        let d = to_field(D);
+        Curve {
+            scalars: Montgomery::new(&U256::from_be_hex(L)),
+            d,
+            d2: field.add(&d, &d),
+            sqrt_m1: to_field(SQRT_M1),
+            b: Point {
+                x,
+                y,
+                z: field.one(),
+                t: field.mul(&x, &y),
+            },
+            identity: Point {
+                x: U256::ZERO,
+                y: field.one(),
+                z: field.one(),
+                t: U256::ZERO,
+            },
+            sqrt_exp: p.overflowing_sub(&U256::from_u32(5)).0.shr(3),
+            field,
+        }
+    }
+
+    // Returns u = y^2 - 1, v = d y^2 + 1 and v^3, for decoding the point
+    // with y, as specified in RFC 8032, section 5.1.3.
+    fn decode_parts(&self, y: &U256) -> (U256, U256, U256) {
+        let f = &self.field;
+        let one = f.one();
+        let yy = f.mul(y, y);
+        let u = f.sub(&yy, &one);
+        let v = f.add(&f.mul(&self.d, &yy), &one);
+        (u, v, f.mul(&f.mul(&v, &v), &v))
+    }
+
+    fn encode(&self, p: &Point) -> [u8; 32] {
+        let f = &self.field;
+        let z_inv = f.invert(&p.z);
+        let x = f.from_montgomery(&f.mul(&p.x, &z_inv));
+        let y = f.from_montgomery(&f.mul(&p.y, &z_inv));
+        let mut bytes = [0; 32];
+        y.write_le_slice(&mut bytes);
+        bytes[31] |= (x.bit(0) as u8) << 7;
+        bytes
+    }
+
+    fn neg(&self, p: &Point) -> Point {
+        let f = &self.field;
+        Point {
+            x: f.sub(&U256::ZERO, &p.x),
+            t: f.sub(&U256::ZERO, &p.t),
+            ..*p
+        }
+    }
+
+    // Returns p + q, with the add-2008-hwcd-3 formulas, which work for any
+    // pair of points.
+    fn add(&self, p: &Point, q: &Point) -> Point {
+        let f = &self.field;
+        let a = f.mul(&f.sub(&p.y, &p.x), &f.sub(&q.y, &q.x));
+        let b = f.mul(&f.add(&p.y, &p.x), &f.add(&q.y, &q.x));
+        let c = f.mul(&f.mul(&p.t, &self.d2), &q.t);
+        let d = f.mul(&p.z, &q.z);
+        let d = f.add(&d, &d);
+        let (e, ff, g, h) = (f.sub(&b, &a), f.sub(&d, &c), f.add(&d, &c), f.add(&b, &a));
+        Point {
+            x: f.mul(&e, &ff),
+            y: f.mul(&g, &h),
+            z: f.mul(&ff, &g),
+            t: f.mul(&e, &h),
+        }
+    }
+
+    // Returns 2p, with the dbl-2008-hwcd formulas.
+    fn double(&self, p: &Point) -> Point {
+        let f = &self.field;
+        let a = f.mul(&p.x, &p.x);
+        let b = f.mul(&p.y, &p.y);
+        let c = f.mul(&p.z, &p.z);
+        let c = f.add(&c, &c);
+        let xy = f.add(&p.x, &p.y);
+        let e = f.sub(&f.sub(&f.mul(&xy, &xy), &a), &b);
+        let g = f.sub(&b, &a);
+        let ff = f.sub(&g, &c);
+        let h = f.sub(&f.sub(&U256::ZERO, &a), &b);
+        Point {
+            x: f.mul(&e, &ff),
+            y: f.mul(&g, &h),
+            z: f.mul(&ff, &g),
+            t: f.mul(&e, &h),
+        }
+    }
+
+    // Returns acc plus the multiple of a point which a NAF digit selects from
+    // the point's table.
+    fn add_digit(&self, acc: &Point, table: &[Point; TABLE_LEN], digit: i8) -> Point {
+        match digit {
+            0 => *acc,
+            1.. => self.add(acc, &table[digit as usize / 2]),
+            _ => self.add(acc, &self.neg(&table[digit.unsigned_abs() as usize / 2])),
+        }
+    }
+}
+
+// The costs of the pieces of a verification, in field multiplications.
+const DOUBLE: u32 = 8;
+const ADD: u32 = 9;
+
+// The NAFs of s and k, which are cheaper to compute again in each execution
+// than to save.
+type Digits = ([i8; 257], [i8; 257]);
+
+// The piece of a verification which a verifier does next.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Step {
+    // Applying the next bit of the exponent which gives x for the public key,
+    // or checking x once the exponent is done.
+    Decompress,
+    // Computing an entry of the tables of odd multiples, of the base point
+    // for the first TABLE_LEN and then of the negated public key.
+    Table(usize),
+    // Doubling the accumulator and adding the digits at the index below
+    // this one.
+    Digit(usize),
+    // Checking that the accumulated point encodes to R.
+    Finish,
+}
+
+/// An Ed25519 verification which is carried out a piece at a time, so that
+/// it can be split across executions, as described in the
+/// [module documentation](super).
+#[derive(Clone, Debug)]
+pub struct Verifier {
+    step: Step,
+    // The y of the public key, and the sign of its x, which is computed from
+    // the power `sqrt` computes, as described in RFC 8032, section 5.1.3.
+    y: U256,
+    sign: bool,
+    sqrt: Pow,
+    // The negated public key, once it has been decoded.
+    q: Point,
+    // The encoded R, as a little-endian integer, and the scalars of
+    // s * b + k * q, which acc accumulates using the tables of odd multiples
+    // of b and q.
+    r: U256,
+    s: U256,
+    k: U256,
+    tables: [[Point; TABLE_LEN]; 2],
+    acc: Point,
+}
+
+impl Verifier {
+    /// Starts verifying a signature of `msg`, after checking that the public
+    /// key and the signature are well formed.
+    ///
+    /// A public key whose y has no point on the curve is only found to be
+    /// invalid by [Verifier::run].
+    pub fn new(public_key: &[u8], msg: &[u8], sig: &[u8]) -> Result<Self, Error> {
+        if public_key.len() != 32 {
+            return Err(Error::InvalidPublicKey);
+        }
+        if sig.len() != 64 {
+            return Err(Error::InvalidSignature);
+        }
+        let curve = Curve::new();
+        let f = &curve.field;
+        let mut y = [0; 32];
+        y.copy_from_slice(public_key);
+        let sign = y[31] >> 7 == 1;
+        y[31] &= 0x7f;
+        let y = U256::from_le_slice(&y);
+        if y >= *f.modulus() {
+            return Err(Error::InvalidPublicKey);
+        }
+        let y = f.to_montgomery(&y);
+        let s = U256::from_le_slice(&sig[32..]);
+        if s >= *curve.scalars.modulus() {
+            return Err(Error::InvalidSignature);
+        }
+
+        let hash = Sha512::new()
+            .chain_update(&sig[..32])
+            .chain_update(public_key)
+            .chain_update(msg)
+            .finalize();
+        let k = curve.scalars.reduce_wide(
+            &U256::from_le_slice(&hash[..32]),
+            &U256::from_le_slice(&hash[32..]),
+        );
+
+        let (u, v, v3) = curve.decode_parts(&y);
+        let v7 = f.mul(&f.mul(&v3, &v3), &v);
+        Ok(Verifier {
+            step: Step::Decompress,
+            y,
+            sign,
+            sqrt: Pow::new(f, &f.mul(&u, &v7), &curve.sqrt_exp),
+            q: curve.identity,
+            r: U256::from_le_slice(&sig[..32]),
+            s,
+            k,
+            tables: [[curve.identity; TABLE_LEN]; 2],
+            acc: curve.identity,
+        })
+    }
+
+    /// Does pieces of the verification until the next one would take the
+    /// field multiplications done past `budget`, and returns the result if
+    /// that finishes the verification.
+    ///
+    /// At least one piece is done, whatever the budget, so that every call
+    /// makes progress. The costliest piece takes about 70 multiplications.
+    pub fn run(&mut self, budget: u32) -> Option<Result<(), Error>> {
+        let curve = Curve::new();
+        let mut digits = None;
+        let mut spent = 0;
+        loop {
+            let cost = match self.step {
+                Step::Decompress if self.sqrt.is_done() => 10,
+                Step::Decompress => self.sqrt.cost(&curve.sqrt_exp),
+                Step::Table(i) if i % TABLE_LEN == 0 => 0,
+                Step::Table(_) => DOUBLE + ADD,
+                Step::Digit(i) => {
+                    let (d1, d2) = self.digits(&mut digits);
+                    DOUBLE + ADD * ((d1[i - 1] != 0) as u32 + (d2[i - 1] != 0) as u32)
+                }
+                Step::Finish => INVERT + 4,
+            };
+            if spent > 0 && spent + cost > budget {
+                return None;
+            }
+            spent += cost;
+            if let Some(result) = self.advance(&curve, &mut digits) {
+                return Some(result);
+            }
+        }
+    }
+
+    // Does the next piece of the verification, and returns the result if it
+    // was the last.
+    fn advance(&mut self, curve: &Curve, digits: &mut Option<Digits>) -> Option<Result<(), Error>> {
+        let f = &curve.field;
+        match self.step {
+            Step::Decompress if self.sqrt.is_done() => {
+                // x^2 = u / v, and x = u v^3 (u v^7)^((p - 5) / 8) is a
+                // square root of either u / v or -u / v.
+                let (u, v, v3) = curve.decode_parts(&self.y);
+                let mut x = f.mul(&f.mul(&u, &v3), &self.sqrt.acc);
+                let vxx = f.mul(&v, &f.mul(&x, &x));
+                if vxx == f.sub(&U256::ZERO, &u) {
+                    x = f.mul(&x, &curve.sqrt_m1);
+                } else if vxx != u {
+                    return Some(Err(Error::InvalidPublicKey));
+                }
+                let x_bits = f.from_montgomery(&x);
+                if x_bits.is_zero() && self.sign {
+                    return Some(Err(Error::InvalidPublicKey));
+                }
+                if x_bits.bit(0) != self.sign {
+                    x = f.sub(&U256::ZERO, &x);
+                }
+                let a = Point {
+                    x,
+                    y: self.y,
+                    z: f.one(),
+                    t: f.mul(&x, &self.y),
+                };
+                self.q = curve.neg(&a);
+                self.step = Step::Table(0);
+            }
+            Step::Decompress => self.sqrt.step(f, &curve.sqrt_exp),
+            Step::Table(i) => {
+                // Each entry is the one before plus 2p, which is cheaper to
+                // compute again for each entry than to save.
+                let (t, j) = (i / TABLE_LEN, i % TABLE_LEN);
+                let p = if t == 0 { curve.b } else { self.q };
+                self.tables[t][j] = match j {
+                    0 => p,
+                    _ => curve.add(&self.tables[t][j - 1], &curve.double(&p)),
+                };
+                self.step = match i + 1 {
+                    TABLES => self.first_digit(digits),
+                    next => Step::Table(next),
+                };
+            }
+            Step::Digit(i) => {
+                let (d1, d2) = self.digits(digits);
+                self.acc = curve.double(&self.acc);
+                self.acc = curve.add_digit(&self.acc, &self.tables[0], d1[i - 1]);
+                self.acc = curve.add_digit(&self.acc, &self.tables[1], d2[i - 1]);
+                self.step = match i {
+                    1 => Step::Finish,
+                    _ => Step::Digit(i - 1),
+                };
+            }
+            Step::Finish => {
+                let mut r = [0; 32];
+                self.r.write_le_slice(&mut r);
+                return Some(match curve.encode(&self.acc) == r {
+                    true => Ok(()),
+                    false => Err(Error::Mismatch),
+                });
+            }
+        }
+        None
+    }
+
+    // Returns the step for the top digit, as doublings of the identity can
+    // be skipped.
+    fn first_digit(&self, digits: &mut Option<Digits>) -> Step {
+        let (d1, d2) = self.digits(digits);
+        let top = (0..d1.len())
+            .rev()
+            .find(|&i| d1[i] != 0 || d2[i] != 0)
+            .map_or(0, |i| i + 1);
+        match top {
+            0 => Step::Finish,
+            _ => Step::Digit(top),
+        }
+    }
+
+    fn digits<'a>(&self, digits: &'a mut Option<Digits>) -> &'a Digits {
+        digits.get_or_insert_with(|| (wnaf(&self.s), wnaf(&self.k)))
+    }
+
+    /// Saves the progress of the verification, for [Verifier::from_words] to
+    /// pick up.
+    pub fn to_words(&self) -> Vec<u32> {
+        let (tag, arg) = match self.step {
+            Step::Decompress => (0, 0),
+            Step::Table(i) => (1, i),
+            Step::Digit(i) => (2, i),
+            Step::Finish => (3, 0),
+        };
+        let mut words = Words::default();
+        words.u32(tag);
+        words.u32(arg as u32);
+        words.u32(self.sign as u32);
+        words.uint(&self.y);
+        self.sqrt.save(&mut words);
+        for value in [&self.r, &self.s, &self.k] {
+            words.uint(value);
+        }
+        for point in [&self.q]
+            .into_iter()
+            .chain(self.tables.iter().flatten())
+            .chain([&self.acc])
+        {
+            point.save(&mut words);
+        }
+        words.0
+    }
+
+    /// Picks up a verification from the words [Verifier::to_words] saved, or
+    /// returns `None` if they are malformed.
+    pub fn from_words(words: &[u32]) -> Option<Self> {
+        let mut words = Reader(words);
+        let step = match (words.u32()?, words.u32()? as usize) {
+            (0, 0) => Step::Decompress,
+            (1, i) if i < TABLES => Step::Table(i),
+            (2, i @ 1..=257) => Step::Digit(i),
+            (3, 0) => Step::Finish,
+            _ => return None,
+        };
+        let sign = match words.u32()? {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+        let y = words.uint()?;
+        let sqrt = Pow::load(&mut words)?;
+        let (r, s, k) = (words.uint()?, words.uint()?, words.uint()?);
+        let q = Point::load(&mut words)?;
+        let mut tables = [[q; TABLE_LEN]; 2];
+        for point in tables.iter_mut().flatten() {
+            *point = Point::load(&mut words)?;
+        }
+        let acc = Point::load(&mut words)?;
+        words.finish(Verifier {
+            step,
+            y,
+            sign,
+            sqrt,
+            q,
+            r,
+            s,
+            k,
+            tables,
+            acc,
+        })
+    }
+}
+
+/// Verifies an Ed25519 signature of `msg`.
+pub fn verify(public_key: &[u8], msg: &[u8], sig: &[u8]) -> Result<(), Error> {
+    let mut verifier = Verifier::new(public_key, msg, sig)?;
+    loop {
+        if let Some(result) = verifier.run(u32::MAX) {
+            return result;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{verify, Verifier};
+    use crate::sig::{from_hex, Error};
+
+    // Tests 1 and 2 from RFC 8032, section 7.1.
+    const VECTORS: [(&str, &str, &str); 2] = [
+        (
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+            "",
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155\
+             5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+        ),
+        (
+            "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+            "72",
+            "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da\
+             085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+        ),
+    ];
+
+    #[test]
+    fn rfc8032() {
+        for (key, msg, sig) in VECTORS {
+            let (key, msg, sig) = (from_hex(key), from_hex(msg), from_hex(sig));
+            assert_eq!(verify(&key, &msg, &sig), Ok(()));
+            assert_eq!(verify(&key, b"x", &sig), Err(Error::Mismatch));
+
+            let mut bad = sig.clone();
+            bad[0] ^= 1;
+            assert_eq!(verify(&key, &msg, &bad), Err(Error::Mismatch));
+            // S must be less than the group order.
+            bad.copy_from_slice(&sig);
+            bad[63] = 0xff;
+            assert_eq!(verify(&key, &msg, &bad), Err(Error::InvalidSignature));
+            assert_eq!(verify(&key, &msg, &sig[..63]), Err(Error::InvalidSignature));
+        }
+    }
+
+    #[test]
+    fn invalid_key() {
+        let (_, _, sig) = VECTORS[0];
+        let sig = from_hex(sig);
+        // There is no point with y = 2.
+        let mut key = [0; 32];
+        key[0] = 2;
+        assert_eq!(verify(&key, b"", &sig), Err(Error::InvalidPublicKey));
+        // y must be less than the field prime.
+        assert_eq!(verify(&[0xff; 32], b"", &sig), Err(Error::InvalidPublicKey));
+        assert_eq!(verify(&[0; 31], b"", &sig), Err(Error::InvalidPublicKey));
+    }
+
+    // Verifies a signature with a verifier which is saved and picked up
+    // again after every budget's worth of pieces, returning the result and
+    // the number of runs it took.
+    fn split(key: &[u8], msg: &[u8], sig: &[u8]) -> (Result<(), Error>, usize) {
+        let mut words = match Verifier::new(key, msg, sig) {
+            Ok(verifier) => verifier.to_words(),
+            Err(err) => return (Err(err), 0),
+        };
+        for runs in 1.. {
+            let mut verifier = Verifier::from_words(&words).unwrap();
+            if let Some(result) = verifier.run(150) {
+                return (result, runs);
+            }
+            words = verifier.to_words();
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn split_verification() {
+        for (key, msg, sig) in VECTORS {
+            let (key, msg, sig) = (from_hex(key), from_hex(msg), from_hex(sig));
+            let (result, runs) = split(&key, &msg, &sig);
+            assert_eq!(result, Ok(()));
+            assert!(runs > 10);
+            assert_eq!(split(&key, b"x", &sig).0, Err(Error::Mismatch));
+        }
+
+        // There is no point with y = 2, which is found once the square root
+        // is done.
+        let sig = from_hex(VECTORS[0].2);
+        let mut key = [0; 32];
+        key[0] = 2;
+        assert_eq!(split(&key, b"", &sig).0, Err(Error::InvalidPublicKey));
+
+        let words = Verifier::new(&from_hex(VECTORS[0].0), b"", &sig)
+            .unwrap()
+            .to_words();
+        assert!(Verifier::from_words(&words[..words.len() - 1]).is_none());
+        let mut bad = words.clone();
+        bad[2] = 2;
+        assert!(Verifier::from_words(&bad).is_none());
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Both schemes are built on [crate::bigint]: field and scalar arithmetic
+//! uses Montgomery multiplication, and points are kept in projective
+//! coordinates, so the only inversions are the final ones. The two scalar
+//! multiplications of a verification share their doublings, and use
+//! windowed non-adjacent forms so that they need few additions. ECDSA hashes
+//! messages with the SHA-256 accelerator. Ed25519 needs SHA-512, which the
+//! accelerator doesn't support, so it is computed in software.
+//!
+//! # Splitting a verification across executions
+//!
+//! A verification takes several times more cycles than a single execution
+//! may run for, so proving one takes a chain of executions. Each scheme has a
+//! `Verifier` which carries out a verification a piece at a time: `run` does
+//! pieces until their cost would exceed a budget, `to_words` saves the
+//! progress at the end of an execution, and `from_words` picks it up in the
+//! next one. Budgets count field multiplications, which are most of the work.
+//! An execution should commit the words it started from and the words it
+//! ended with, so that whoever checks the receipts can check that each one
+//! carries on from the last.
+//!
+//! ```
+//! use risc0_zkvm_guest::sig::{ecdsa, Error};
+//!
+//! // Runs one execution's worth of a verification, returning the words to
+//! // pass to the next execution, or the result.
+//! fn step(
+//!     curve: &ecdsa::Curve,
+//!     public_key: &[u8],
+//!     digest: &[u8; 32],
+//!     sig: &[u8],
+//!     words: &[u32],
+//! ) -> Result<Option<Vec<u32>>, Error> {
+//!     let mut verifier = match words.is_empty() {
+//!         true => ecdsa::Verifier::new(curve, public_key, digest, sig)?,
+//!         false => ecdsa::Verifier::from_words(words).expect("Malformed words"),
+//!     };
+//!     match verifier.run(curve, 100) {
+//!         Some(result) => result.map(|()| None),
+//!         None => Ok(Some(verifier.to_words())),
+//!     }
+//! }
+//! ```
+
+use _alloc::vec::Vec;
+use core::fmt::{self, Display};
+
+use crate::bigint::{Montgomery, Uint, U256};
+
+/// ECDSA signatures over secp256k1 and P-256.
+pub mod ecdsa;
+
+/// Ed25519 signatures, as specified in RFC 8032.
+pub mod ed25519;
+
+/// An error from verifying a signature.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The public key is malformed, or isn't a point on the curve.
+    InvalidPublicKey,
+    /// The signature is malformed, or has an out of range scalar.
+    InvalidSignature,
+    /// The signature doesn't match the message and public key.
+    Mismatch,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Error::InvalidPublicKey => "Invalid public key",
+            Error::InvalidSignature => "Invalid signature",
+            Error::Mismatch => "Signature mismatch",
+        })
+    }
+}
+
+// The width of the NAFs used for scalar multiplication, which use tables of
+// 2^(WINDOW - 2) odd multiples of a point.
+const WINDOW: usize = 5;
+const TABLE_LEN: usize = 1 << (WINDOW - 2);
+// A verification's two tables have this many entries between them.
+const TABLES: usize = 2 * TABLE_LEN;
+
+// Returns the width-WINDOW non-adjacent form of k, least significant digit
+// first: each digit is 0 or odd and less than 2^(WINDOW - 1) in absolute
+// value, and any WINDOW consecutive digits have at most one nonzero.
+//
+// A scalar multiplication then needs a doubling per digit, but an addition
+// only for about one digit in WINDOW + 1, of the table entry for the digit or
+// its negation.
+fn wnaf(k: &U256) -> [i8; 257] {
+    let mut digits = [0; 257];
+    // An extra word holds the carry from rounding a digit up.
+    let mut k = Uint::<9>([
+        k.0[0], k.0[1], k.0[2], k.0[3], k.0[4], k.0[5], k.0[6], k.0[7], 0,
+    ]);
+    let mut i = 0;
+    while !k.is_zero() {
+        if k.0[0] & 1 == 1 {
+            let mut digit = (k.0[0] & ((1 << WINDOW) - 1)) as i32;
+            if digit >= 1 << (WINDOW - 1) {
+                digit -= 1 << WINDOW;
+            }
+            k = match digit < 0 {
+                true => k.overflowing_add(&Uint::from_u32(-digit as u32)).0,
+                false => k.overflowing_sub(&Uint::from_u32(digit as u32)).0,
+            };
+            digits[i] = digit as i8;
+        }
+        k = k.shr(1);
+        i += 1;
+    }
+    digits
+}
+
+// The cost of an inversion, in multiplications.
+const INVERT: u32 = 64;
+
+// An exponentiation, in Montgomery form, which is carried out a bit of the
+// exponent at a time from the top, so that it can be split across
+// executions. The exponent is passed to each call rather than kept.
+#[derive(Clone, Copy, Debug)]
+struct Pow {
+    base: U256,
+    acc: U256,
+    // The number of bits of the exponent still to be applied.
+    bits: usize,
+}
+
+impl Pow {
+    fn new(field: &Montgomery<8>, base: &U256, exp: &U256) -> Self {
+        Pow {
+            base: *base,
+            acc: field.one(),
+            bits: exp.bits(),
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.bits == 0
+    }
+
+    // Returns the number of multiplications the next bit takes.
+    fn cost(&self, exp: &U256) -> u32 {
+        1 + exp.bit(self.bits - 1) as u32
+    }
+
+    fn step(&mut self, field: &Montgomery<8>, exp: &U256) {
+        self.bits -= 1;
+        self.acc = field.mul(&self.acc, &self.acc);
+        if exp.bit(self.bits) {
+            self.acc = field.mul(&self.acc, &self.base);
+        }
+    }
+
+    fn save(&self, words: &mut Words) {
+        words.uint(&self.base);
+        words.uint(&self.acc);
+        words.u32(self.bits as u32);
+    }
+
+    fn load(words: &mut Reader) -> Option<Self> {
+        let (base, acc) = (words.uint()?, words.uint()?);
+        let bits = words.u32()? as usize;
+        match bits <= 256 {
+            true => Some(Pow { base, acc, bits }),
+            false => None,
+        }
+    }
+}
+
+// The words a verifier saves its progress in.
+#[derive(Default)]
+struct Words(Vec<u32>);
+
+impl Words {
+    fn u32(&mut self, value: u32) {
+        self.0.push(value);
+    }
+
+    fn uint(&mut self, value: &U256) {
+        self.0.extend_from_slice(&value.0);
+    }
+}
+
+// Reads back the words a verifier saved.
+struct Reader<'a>(&'a [u32]);
+
+impl<'a> Reader<'a> {
+    fn u32(&mut self) -> Option<u32> {
+        let (first, rest) = self.0.split_first()?;
+        self.0 = rest;
+        Some(*first)
+    }
+
+    fn uint(&mut self) -> Option<U256> {
+        let mut value = U256::ZERO;
+        for word in value.0.iter_mut() {
+            *word = self.u32()?;
+        }
+        Some(value)
+    }
+
+    // Returns the value once all the words have been read.
+    fn finish<T>(self, value: T) -> Option<T> {
+        match self.0.is_empty() {
+            true => Some(value),
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+fn from_hex(hex: &str) -> std::vec::Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{wnaf, WINDOW};
+    use crate::bigint::{Uint, U256};
+
+    #[test]
+    fn naf() {
+        let max =
+            U256::from_be_hex("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff");
+        let values = [
+            U256::ZERO,
+            U256::ONE,
+            Uint::from_u32(0x1f),
+            Uint::from_u32(0xdead_beef),
+            max,
+        ];
+        for k in values {
+            let digits = wnaf(&k);
+            // Summing the digits from the top gives back k.
+            let mut sum = Uint::<9>::ZERO;
+            for digit in digits.iter().rev() {
+                sum = sum.overflowing_add(&sum).0;
+                sum = match *digit < 0 {
+                    true => sum.overflowing_sub(&Uint::from_u32(digit.unsigned_abs() as u32)),
+                    false => sum.overflowing_add(&Uint::from_u32(*digit as u32)),
+                }
+                .0;
+            }
+            assert_eq!(sum.0[..8], k.0);
+            assert_eq!(sum.0[8], 0);
+
+            for (i, digit) in digits.iter().enumerate() {
+                if *digit != 0 {
+                    assert!(digit % 2 != 0 && digit.unsigned_abs() < 1 << (WINDOW - 1));
+                    let next = &digits[i + 1..(i + WINDOW).min(digits.len())];
+                    assert!(next.iter().all(|digit| *digit == 0));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use _alloc::{format, string::String, vec::Vec};
+
+use core::panic::PanicInfo;
+
+pub use risc0_zkvm_platform::{LIST_TESTS, TEST_RESULT_CHANNEL};
+
+use crate::env;
+
+// Whether a test is running, so a panic reports its failure.
+static mut RUNNING: bool = false;
+
+/// Runs one of the given tests, as chosen by the host.
+///
+/// This is the entrypoint of methods defined with [crate::test_entry]. The
+/// host provides either the index of the test to run, or [LIST_TESTS] to
+/// receive the names of all the tests as private output. The name of the
+/// test that was run is committed to the journal, and its result is written
+/// as a `Result<(), String>` to [TEST_RESULT_CHANNEL]. A test which panics
+/// reports its panic message and exits with code 1, so its failure is
+/// proven like a pass is.
+pub fn run(tests: &[(&str, fn())]) {
+    let index: u32 = env::read();
+    if index == LIST_TESTS {
+        let names: Vec<&str> = tests.iter().map(|(name, _)| *name).collect();
+        env::write(&names);
+        return;
+    }
+    let (name, test) = tests[index as usize];
+    env::commit(&name);
+    unsafe { RUNNING = true };
+    test();
+    unsafe { RUNNING = false };
+    env::write_to(TEST_RESULT_CHANNEL, &Ok::<(), String>(()));
+}
+
+// Called by the panic handler. Reports the failure of the running test, if
+// there is one. A panic while reporting it faults as usual.
+pub(crate) fn fail(panic_info: &PanicInfo) {
+    if unsafe { !RUNNING } {
+        return;
+    }
+    unsafe { RUNNING = false };
+    env::write_to(
+        TEST_RESULT_CHANNEL,
+        &Err::<(), String>(format!("{}", panic_info)),
+    );
+    env::exit(1);
+}
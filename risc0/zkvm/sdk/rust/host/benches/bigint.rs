@@ -0,0 +1,45 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Reports the number of cycles the guest's bigint module takes for a Montgomery
+// multiplication, by comparing the cycles taken by executions of its loop with
+// different numbers of iterations.
+
+use risc0_zkvm_host::Prover;
+use risc0_zkvm_methods::methods::{BIGINT_ID, BIGINT_PATH};
+use risc0_zkvm_serde::to_vec;
+
+const ITERATIONS: u32 = 64;
+
+fn cycles(bits: u32, iterations: u32) -> usize {
+    let mut prover = Prover::new(BIGINT_PATH, BIGINT_ID).unwrap();
+    prover
+        .add_input(&to_vec(&(bits, iterations)).unwrap())
+        .unwrap();
+    prover.execute().unwrap().get_cycles().unwrap()
+}
+
+fn main() {
+    for bits in [256, 384] {
+        // Subtract the cost of everything but the loop.
+        let overhead = cycles(bits, 0);
+        let total = cycles(bits, ITERATIONS) - overhead;
+        println!(
+            "bigint/mul/{}: {} cycles ({} iterations)",
+            bits,
+            total / ITERATIONS as usize,
+            ITERATIONS
+        );
+    }
+}
@@ -0,0 +1,45 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Reports the number of cycles the guest's memory functions take, by comparing
+// the cycles taken by an execution which runs each operation with one which
+// runs none.
+
+use risc0_zkvm_host::Prover;
+use risc0_zkvm_methods::methods::{MEMOPS_ID, MEMOPS_PATH};
+use risc0_zkvm_serde::to_vec;
+
+const OPS: [&str; 6] = [
+    "memcpy",
+    "memcpy unaligned",
+    "byte loop",
+    "memset",
+    "memcmp",
+    "serde",
+];
+
+fn cycles(op: &str, strings: &[String]) -> usize {
+    let mut prover = Prover::new(MEMOPS_PATH, MEMOPS_ID).unwrap();
+    prover.add_input(&to_vec(&op).unwrap()).unwrap();
+    prover.add_input(&to_vec(&strings).unwrap()).unwrap();
+    prover.execute().unwrap().get_cycles().unwrap()
+}
+
+fn main() {
+    let strings: Vec<String> = (0..64).map(|i| format!("{:064}", i)).collect();
+    let overhead = cycles("none", &strings);
+    for op in OPS {
+        println!("memops/{}: {} cycles", op, cycles(op, &strings) - overhead);
+    }
+}
@@ -12,13 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{ffi::CStr, os::raw::c_char};
+use std::{
+    ffi::{c_void, CStr},
+    os::raw::c_char,
+};
 
 use crate::exception::Exception;
 
 pub(crate) enum RawString {}
 pub(crate) enum RawProver {}
 pub(crate) enum RawReceipt {}
+pub(crate) enum RawExecution {}
+
+pub(crate) type OutputCallback = extern "C" fn(ctx: *mut c_void, buf: *const u8, len: usize);
 
 #[repr(C)]
 pub(crate) struct RawError {
@@ -74,6 +80,33 @@ extern "C" {
         len: usize,
     );
 
+    pub(crate) fn risc0_prover_add_arg(
+        err: *mut RawError,
+        prover: *mut RawProver,
+        arg: *const c_char,
+    );
+
+    pub(crate) fn risc0_prover_set_env(
+        err: *mut RawError,
+        prover: *mut RawProver,
+        key: *const c_char,
+        value: *const c_char,
+    );
+
+    pub(crate) fn risc0_prover_add_file(
+        err: *mut RawError,
+        prover: *mut RawProver,
+        name: *const c_char,
+        buf: *const u8,
+        len: usize,
+    );
+
+    pub(crate) fn risc0_prover_set_context(
+        err: *mut RawError,
+        prover: *mut RawProver,
+        buf: *const u32,
+    );
+
     pub(crate) fn risc0_prover_get_output_buf(
         err: *mut RawError,
         prover: *mut RawProver,
@@ -81,15 +114,49 @@ extern "C" {
 
     pub(crate) fn risc0_prover_get_output_len(err: *mut RawError, prover: *mut RawProver) -> usize;
 
+    pub(crate) fn risc0_prover_get_channel_buf(
+        err: *mut RawError,
+        prover: *mut RawProver,
+        channel: u32,
+    ) -> *const u8;
+
+    pub(crate) fn risc0_prover_get_channel_len(
+        err: *mut RawError,
+        prover: *mut RawProver,
+        channel: u32,
+    ) -> usize;
+
+    pub(crate) fn risc0_prover_set_output_callback(
+        err: *mut RawError,
+        prover: *mut RawProver,
+        channel: u32,
+        callback: OutputCallback,
+        ctx: *mut c_void,
+    );
+
     pub(crate) fn risc0_prover_run(err: *mut RawError, prover: *mut RawProver)
         -> *const RawReceipt;
 
-    pub(crate) fn risc0_receipt_verify(
+    #[cfg(feature = "dev-mode")]
+    pub(crate) fn risc0_prover_run_dev_mode(
+        err: *mut RawError,
+        prover: *mut RawProver,
+    ) -> *const RawReceipt;
+
+    pub(crate) fn risc0_receipt_verify_with_exit_code(
         err: *mut RawError,
         receipt: *const RawReceipt,
         method_id: *const u8,
         method_id_len: usize,
-    );
+    ) -> u32;
+
+    #[cfg(feature = "dev-mode")]
+    pub(crate) fn risc0_receipt_verify_allowing_dev_mode(
+        err: *mut RawError,
+        receipt: *const RawReceipt,
+        method_id: *const u8,
+        method_id_len: usize,
+    ) -> u32;
 
     pub(crate) fn risc0_receipt_get_seal_buf(
         err: *mut RawError,
@@ -111,5 +178,37 @@ extern "C" {
         receipt: *const RawReceipt,
     ) -> usize;
 
+    pub(crate) fn risc0_receipt_get_exit_code(
+        err: *mut RawError,
+        receipt: *const RawReceipt,
+    ) -> u32;
+
     pub(crate) fn risc0_receipt_free(err: *mut RawError, receipt: *const RawReceipt);
+
+    pub(crate) fn risc0_prover_execute(
+        err: *mut RawError,
+        prover: *mut RawProver,
+    ) -> *const RawExecution;
+
+    pub(crate) fn risc0_execution_get_journal_buf(
+        err: *mut RawError,
+        execution: *const RawExecution,
+    ) -> *const u8;
+
+    pub(crate) fn risc0_execution_get_journal_len(
+        err: *mut RawError,
+        execution: *const RawExecution,
+    ) -> usize;
+
+    pub(crate) fn risc0_execution_get_exit_code(
+        err: *mut RawError,
+        execution: *const RawExecution,
+    ) -> u32;
+
+    pub(crate) fn risc0_execution_get_cycles(
+        err: *mut RawError,
+        execution: *const RawExecution,
+    ) -> usize;
+
+    pub(crate) fn risc0_execution_free(err: *mut RawError, execution: *const RawExecution);
 }
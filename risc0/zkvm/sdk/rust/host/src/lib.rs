@@ -15,10 +15,14 @@
 #![deny(missing_docs)]
 #![doc = include_str!("../README.md")]
 
-use std::{ffi::CString, mem};
+use std::{
+    ffi::{c_void, CString},
+    mem, panic, process,
+};
 
 mod exception;
 mod ffi;
+pub mod test_runner;
 
 pub use exception::Exception;
 
@@ -28,6 +32,23 @@ mod bridge {}
 /// A Result specialized for [Exception].
 pub type Result<T> = std::result::Result<T, Exception>;
 
+// Marks the fake seal of a dev-mode receipt, after the output registers.
+const DEV_MODE_SEAL: u32 = 0x5644_3052;
+
+/// Returns whether dev mode is enabled, in which case [Prover::run] makes
+/// receipts without proving them and [Receipt::verify] accepts those.
+///
+/// Dev mode is INSECURE, since a dev-mode receipt attests to nothing. It
+/// requires both building this crate with the `dev-mode` feature and setting
+/// the `RISC0_DEV_MODE` environment variable to `1` or `true`.
+pub fn dev_mode() -> bool {
+    cfg!(feature = "dev-mode")
+        && matches!(
+            std::env::var("RISC0_DEV_MODE").as_deref(),
+            Ok("1") | Ok("true")
+        )
+}
+
 /// A record attesting to the correct execution of a 'method'.
 ///
 /// Consists of:
@@ -37,9 +58,39 @@ pub struct Receipt {
     ptr: *const ffi::RawReceipt,
 }
 
+/// The result of executing a method with [Prover::execute], which runs it
+/// without proving it.
+///
+/// Consists of the journal, the code the method exited with, and the number
+/// of cycles it ran for. Nothing attests to it, so it is only useful to the
+/// host which ran it.
+pub struct ExecutionResult {
+    ptr: *const ffi::RawExecution,
+}
+
 /// The prover generates a [Receipt] by executing a given method in a ZKVM.
 pub struct Prover {
     ptr: *mut ffi::RawProver,
+    // Boxed again so the C++ prover can hold a thin pointer to each one.
+    callbacks: Vec<Box<OutputCallback>>,
+}
+
+type OutputCallback = Box<dyn FnMut(&[u8])>;
+
+// Passes a write to an output channel from the C++ prover to the callback
+// registered for it.
+extern "C" fn call_output_callback(ctx: *mut c_void, buf: *const u8, len: usize) {
+    // SAFETY: ctx is one of the callbacks owned by the Prover, which outlive
+    // the C++ prover, and buf holds len bytes for the duration of the call.
+    let callback = unsafe { &mut *(ctx as *mut OutputCallback) };
+    let data: &[u8] = match len {
+        0 => &[],
+        _ => unsafe { std::slice::from_raw_parts(buf, len) },
+    };
+    // Unwinding into C++ is undefined behavior.
+    if panic::catch_unwind(panic::AssertUnwindSafe(|| callback(data))).is_err() {
+        process::abort();
+    }
 }
 
 fn into_words(slice: &[u8]) -> Result<Vec<u32>> {
@@ -59,12 +110,53 @@ fn into_words(slice: &[u8]) -> Result<Vec<u32>> {
 impl Receipt {
     /// Verify that the current [Receipt] is a valid result of executing the
     /// method associated with the given method ID in a ZKVM.
+    ///
+    /// A receipt for an execution which exited with a failure is rejected;
+    /// use [Receipt::verify_with_exit_code] to accept those as well.
     pub fn verify(&self, method_id: &[u8]) -> Result<()> {
-        let mut err = ffi::RawError::default();
-        unsafe {
-            ffi::risc0_receipt_verify(&mut err, self.ptr, method_id.as_ptr(), method_id.len())
+        match self.verify_with_exit_code(method_id)? {
+            0 => Ok(()),
+            code => Err(Exception::new(&format!(
+                "Receipt is for an execution which exited with code {}",
+                code
+            ))),
+        }
+    }
+
+    /// Verify that the current [Receipt] is a valid result of executing the
+    /// method associated with the given method ID in a ZKVM, and return the
+    /// code the method exited with.
+    ///
+    /// An exit code of 0 means the method halted normally. Otherwise the
+    /// method exited with a failure, and the journal holds whatever it
+    /// committed before exiting.
+    pub fn verify_with_exit_code(&self, method_id: &[u8]) -> Result<u32> {
+        #[cfg(feature = "dev-mode")]
+        let verify = match dev_mode() {
+            true => ffi::risc0_receipt_verify_allowing_dev_mode,
+            false => ffi::risc0_receipt_verify_with_exit_code,
         };
-        ffi::check(err, || ())
+        #[cfg(not(feature = "dev-mode"))]
+        let verify = ffi::risc0_receipt_verify_with_exit_code;
+        let mut err = ffi::RawError::default();
+        let code = unsafe { verify(&mut err, self.ptr, method_id.as_ptr(), method_id.len()) };
+        ffi::check(err, || code)
+    }
+
+    /// Returns whether this is a dev-mode receipt, made without proving the
+    /// method, whose seal is fake. Such a receipt is rejected unless
+    /// [dev_mode] is enabled.
+    pub fn is_dev_mode(&self) -> Result<bool> {
+        let seal = self.get_seal()?;
+        Ok(seal.len() == 10 && seal[9] == DEV_MODE_SEAL)
+    }
+
+    /// Provides the code the method exited with, which is 0 unless it exited
+    /// with a failure.
+    pub fn get_exit_code(&self) -> Result<u32> {
+        let mut err = ffi::RawError::default();
+        let code = unsafe { ffi::risc0_receipt_get_exit_code(&mut err, self.ptr) };
+        ffi::check(err, || code)
     }
 
     /// Provides access to the `seal` of a [Receipt].
@@ -99,6 +191,46 @@ impl Receipt {
     }
 }
 
+impl ExecutionResult {
+    /// Provides the code the method exited with, which is 0 unless it exited
+    /// with a failure.
+    pub fn get_exit_code(&self) -> Result<u32> {
+        let mut err = ffi::RawError::default();
+        let code = unsafe { ffi::risc0_execution_get_exit_code(&mut err, self.ptr) };
+        ffi::check(err, || code)
+    }
+
+    /// Provides the number of cycles the method ran for, including loading
+    /// it.
+    pub fn get_cycles(&self) -> Result<usize> {
+        let mut err = ffi::RawError::default();
+        let cycles = unsafe { ffi::risc0_execution_get_cycles(&mut err, self.ptr) };
+        ffi::check(err, || cycles)
+    }
+
+    /// Provides access to the `journal` the method committed.
+    pub fn get_journal(&self) -> Result<&[u8]> {
+        unsafe {
+            let mut err = ffi::RawError::default();
+            let buf = ffi::risc0_execution_get_journal_buf(&mut err, self.ptr);
+            let buf = ffi::check(err, || buf)?;
+            let mut err = ffi::RawError::default();
+            let len = ffi::risc0_execution_get_journal_len(&mut err, self.ptr);
+            let len = ffi::check(err, || len)?;
+            Ok(match len {
+                0 => &[],
+                _ => std::slice::from_raw_parts(buf, len),
+            })
+        }
+    }
+
+    /// Provides access to the `journal` the method committed as a
+    /// [`Vec<u32>`].
+    pub fn get_journal_vec(&self) -> Result<Vec<u32>> {
+        into_words(self.get_journal()?)
+    }
+}
+
 impl Prover {
     /// Create a new [Prover] with the given method (specified via `elf_path`)
     /// and an associated method ID (specified via `method_id`).
@@ -113,7 +245,10 @@ impl Prover {
                 method_id.len(),
             )
         };
-        ffi::check(err, || Prover { ptr })
+        ffi::check(err, || Prover {
+            ptr,
+            callbacks: Vec::new(),
+        })
     }
 
     /// Provide private input data that is availble to guest-side method code
@@ -131,6 +266,56 @@ impl Prover {
         ffi::check(err, || ())
     }
 
+    /// Add arguments for guest-side method code, which it gets from
+    /// `env::args`.
+    ///
+    /// Arguments, environment variables and files are kept apart from the
+    /// input data provided with [Prover::add_input], and share the input
+    /// region of the ZKVM with it.
+    pub fn args(&mut self, args: &[&str]) -> Result<()> {
+        for arg in args {
+            let mut err = ffi::RawError::default();
+            let arg = CString::new(*arg).unwrap();
+            unsafe { ffi::risc0_prover_add_arg(&mut err, self.ptr, arg.as_ptr()) };
+            ffi::check(err, || ())?;
+        }
+        Ok(())
+    }
+
+    /// Set an environment variable for guest-side method code, which it gets
+    /// from `env::var`, replacing any previous value.
+    pub fn env(&mut self, key: &str, value: &str) -> Result<()> {
+        let mut err = ffi::RawError::default();
+        let key = CString::new(key).unwrap();
+        let value = CString::new(value).unwrap();
+        unsafe { ffi::risc0_prover_set_env(&mut err, self.ptr, key.as_ptr(), value.as_ptr()) };
+        ffi::check(err, || ())
+    }
+
+    /// Attach a named read-only file, which guest-side method code can `open`
+    /// by name.
+    pub fn add_file(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let mut err = ffi::RawError::default();
+        let name = CString::new(name).unwrap();
+        unsafe {
+            ffi::risc0_prover_add_file(&mut err, self.ptr, name.as_ptr(), data.as_ptr(), data.len())
+        };
+        ffi::check(err, || ())
+    }
+
+    /// Set a public context, such as a nonce or the digest of a request,
+    /// which guest-side method code commits to the journal ahead of anything
+    /// else.
+    ///
+    /// A verifier which chose the context can then check it with
+    /// `Receipt::verify_with_context`, so a receipt made for one request can't
+    /// be replayed for another.
+    pub fn set_context(&mut self, context: &[u32; 8]) -> Result<()> {
+        let mut err = ffi::RawError::default();
+        unsafe { ffi::risc0_prover_set_context(&mut err, self.ptr, context.as_ptr()) };
+        ffi::check(err, || ())
+    }
+
     /// Provide access to private output data written by guest-side method code.
     pub fn get_output(&self) -> Result<&[u8]> {
         unsafe {
@@ -152,12 +337,91 @@ impl Prover {
         into_words(self.get_output()?)
     }
 
+    /// Provide access to private output data written by guest-side method code
+    /// to the given channel with `env::write_to`.
+    ///
+    /// Channel 0 is the same as [Prover::get_output].
+    pub fn get_channel_output(&self, channel: u32) -> Result<&[u8]> {
+        unsafe {
+            let mut err = ffi::RawError::default();
+            let buf = ffi::risc0_prover_get_channel_buf(&mut err, self.ptr, channel);
+            let buf = ffi::check(err, || buf)?;
+            let mut err = ffi::RawError::default();
+            let len = ffi::risc0_prover_get_channel_len(&mut err, self.ptr, channel);
+            let len = ffi::check(err, || len)?;
+            Ok(match len {
+                0 => &[],
+                _ => std::slice::from_raw_parts(buf, len),
+            })
+        }
+    }
+
+    /// Provide access to private output data written by guest-side method code
+    /// to the given channel.
+    ///
+    /// This returns the data as a [`Vec<u32>`].
+    pub fn get_channel_output_vec(&self, channel: u32) -> Result<Vec<u32>> {
+        into_words(self.get_channel_output(channel)?)
+    }
+
+    /// Pass each write by guest-side method code to the given channel to
+    /// `callback` as it happens, instead of collecting it for
+    /// [Prover::get_channel_output].
+    ///
+    /// This replaces any callback already set for the channel.
+    pub fn on_output<F: FnMut(&[u8]) + 'static>(
+        &mut self,
+        channel: u32,
+        callback: F,
+    ) -> Result<()> {
+        let mut callback: Box<OutputCallback> = Box::new(Box::new(callback));
+        let ctx = &mut *callback as *mut OutputCallback as *mut c_void;
+        let mut err = ffi::RawError::default();
+        unsafe {
+            ffi::risc0_prover_set_output_callback(
+                &mut err,
+                self.ptr,
+                channel,
+                call_output_callback,
+                ctx,
+            )
+        };
+        ffi::check(err, || self.callbacks.push(callback))
+    }
+
     /// Execute the ZKVM to produce a [Receipt].
+    ///
+    /// If [dev_mode] is enabled, the method is only executed, and the receipt
+    /// has the real journal but a fake seal which proves nothing.
     pub fn run(&self) -> Result<Receipt> {
+        #[cfg(feature = "dev-mode")]
+        if dev_mode() {
+            return self.run_dev_mode();
+        }
         let mut err = ffi::RawError::default();
         let ptr = unsafe { ffi::risc0_prover_run(&mut err, self.ptr) };
         ffi::check(err, || Receipt { ptr })
     }
+
+    #[cfg(feature = "dev-mode")]
+    fn run_dev_mode(&self) -> Result<Receipt> {
+        let mut err = ffi::RawError::default();
+        let ptr = unsafe { ffi::risc0_prover_run_dev_mode(&mut err, self.ptr) };
+        ffi::check(err, || Receipt { ptr })
+    }
+
+    /// Execute the method without proving it, which takes a fraction of the
+    /// time [Prover::run] does.
+    ///
+    /// This is useful for development, for tests which only check the
+    /// journal or the output, and for trying out untrusted input before
+    /// proving. The output is available afterwards, as it is after
+    /// [Prover::run].
+    pub fn execute(&self) -> Result<ExecutionResult> {
+        let mut err = ffi::RawError::default();
+        let ptr = unsafe { ffi::risc0_prover_execute(&mut err, self.ptr) };
+        ffi::check(err, || ExecutionResult { ptr })
+    }
 }
 
 impl Drop for Receipt {
@@ -168,6 +432,14 @@ impl Drop for Receipt {
     }
 }
 
+impl Drop for ExecutionResult {
+    fn drop(&mut self) {
+        let mut err = ffi::RawError::default();
+        unsafe { ffi::risc0_execution_free(&mut err, self.ptr) };
+        ffi::check(err, || ()).unwrap()
+    }
+}
+
 impl Drop for Prover {
     fn drop(&mut self) {
         let mut err = ffi::RawError::default();
@@ -186,8 +458,15 @@ mod test {
     use super::Prover;
     use anyhow::Result;
     use risc0_zkvm_core::Digest;
-    use risc0_zkvm_methods::methods::{FAIL_ID, FAIL_PATH, IO_ID, IO_PATH, SHA_ID, SHA_PATH};
+    use risc0_zkvm_methods::methods::{
+        ARGS_ID, ARGS_PATH, CC_ID, CC_PATH, CHANNELS_ID, CHANNELS_PATH, EXIT_ID, EXIT_PATH,
+        FAIL_ID, FAIL_PATH, FILES_ID, FILES_PATH, GUEST_TESTS_ID, GUEST_TESTS_PATH, IO_ID, IO_PATH,
+        JOURNAL_OVERFLOW_ID, JOURNAL_OVERFLOW_PATH, MERKLE_JOURNAL_ID, MERKLE_JOURNAL_PATH,
+        RECURSE_ID, RECURSE_PATH, SHA_ID, SHA_PATH, SIG_ID, SIG_PATH,
+    };
+    use risc0_zkvm_platform::{memory::COMMIT, WORD_SIZE};
     use risc0_zkvm_serde::{from_slice, to_vec};
+    use std::{cell::RefCell, rc::Rc};
 
     #[test]
     fn sha() {
@@ -277,4 +556,360 @@ mod test {
         let prover = Prover::new(FAIL_PATH, FAIL_ID).unwrap();
         assert!(prover.run().is_err());
     }
+
+    #[test]
+    fn channels() {
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let mut prover = Prover::new(CHANNELS_PATH, CHANNELS_ID).unwrap();
+        prover.add_input(&to_vec(&3u32).unwrap()).unwrap();
+        let sink = received.clone();
+        prover
+            .on_output(2, move |buf| sink.borrow_mut().extend_from_slice(buf))
+            .unwrap();
+        let receipt = prover.run().unwrap();
+        receipt.verify(CHANNELS_ID).unwrap();
+        assert_eq!(prover.get_output_vec().unwrap(), [3]);
+        assert_eq!(prover.get_channel_output_vec(0).unwrap(), [3]);
+        assert_eq!(prover.get_channel_output_vec(1).unwrap(), [1, 2, 3]);
+        // Channel 2 went to the callback instead.
+        assert!(prover.get_channel_output(2).unwrap().is_empty());
+        assert_eq!(*received.borrow(), [2, 0, 0, 0, 4, 0, 0, 0, 6, 0, 0, 0]);
+        assert!(prover.get_channel_output(3).unwrap().is_empty());
+    }
+
+    fn run_journal_overflow(capacity: u32, count: u32, input: &[u32]) {
+        let mut prover = Prover::new(JOURNAL_OVERFLOW_PATH, JOURNAL_OVERFLOW_ID).unwrap();
+        prover
+            .add_input(&to_vec(&(capacity, count)).unwrap())
+            .unwrap();
+        prover.add_input(&to_vec(input).unwrap()).unwrap();
+        let receipt = prover.run().unwrap();
+        receipt.verify(JOURNAL_OVERFLOW_ID).unwrap();
+        let mut expected: Vec<u32> = (0..count).chain([count]).chain(0..count).collect();
+        for _ in 0..2 {
+            expected.push(input.len() as u32);
+            expected.extend_from_slice(input);
+        }
+        assert_eq!(receipt.get_journal_vec().unwrap(), expected);
+    }
+
+    #[test]
+    fn journal_overflow() {
+        // Lowering the commit capacity sends a small journal down the path it
+        // takes when it outgrows the commit region. The journals are 63 and 65
+        // words long, so the SHA trailer fits in the last block for one and
+        // not the other.
+        for (capacity, count) in [(8u32, 30u32), (20, 31)] {
+            run_journal_overflow(capacity, count, &[]);
+        }
+    }
+
+    #[test]
+    fn journal_larger_than_commit_region() {
+        // The first copy of the input fits in the commit region, and the
+        // second takes the journal past the end of it.
+        let input: Vec<u32> = (0..(COMMIT.len() / WORD_SIZE / 2) as u32).collect();
+        run_journal_overflow(0, 0, &input);
+    }
+
+    #[test]
+    fn stack_overflow() {
+        let mut prover = Prover::new(RECURSE_PATH, RECURSE_ID).unwrap();
+        prover.add_input(&to_vec(&100u32).unwrap()).unwrap();
+        let receipt = prover.run().unwrap();
+        let journal = receipt.get_journal_vec().unwrap();
+        assert_eq!(from_slice::<u32>(&journal).unwrap(), 5050);
+
+        // Each level of recursion takes more than 256 bytes, so this is more
+        // than the 256 KB stack can hold.
+        let mut prover = Prover::new(RECURSE_PATH, RECURSE_ID).unwrap();
+        prover.add_input(&to_vec(&2000u32).unwrap()).unwrap();
+        match prover.run() {
+            Ok(_) => panic!("Expected a stack overflow"),
+            Err(err) => assert!(err.what().starts_with("StackOverflow: pc = "), "{}", err),
+        }
+    }
+
+    #[test]
+    fn args() {
+        let mut prover = Prover::new(ARGS_PATH, ARGS_ID).unwrap();
+        prover.args(&["run", "--fast"]).unwrap();
+        prover.env("MODE", "debug").unwrap();
+        prover.env("MODE", "release").unwrap();
+        let receipt = prover.run().unwrap();
+        let journal = receipt.get_journal_vec().unwrap();
+        assert_eq!(
+            from_slice::<(Vec<&str>, Option<&str>)>(&journal).unwrap(),
+            (vec!["run", "--fast"], Some("release"))
+        );
+    }
+
+    #[test]
+    fn context() {
+        let context = Digest::new([1, 2, 3, 4, 5, 6, 7, 8]);
+        let mut prover = Prover::new(ARGS_PATH, ARGS_ID).unwrap();
+        prover.set_context(context.get()).unwrap();
+        let receipt = prover.run().unwrap();
+        receipt.verify(ARGS_ID).unwrap();
+        // The seal marks the journal as starting with the context.
+        assert_ne!(receipt.get_seal().unwrap()[8] & 1 << 29, 0);
+        let journal = receipt.get_journal_vec().unwrap();
+        assert_eq!(journal[..8], *context.get());
+        assert_eq!(
+            from_slice::<(Vec<&str>, Option<&str>)>(&journal[8..]).unwrap(),
+            (vec![], None)
+        );
+
+        // A failure keeps the mark, along with the exit code.
+        let mut prover = Prover::new(EXIT_PATH, EXIT_ID).unwrap();
+        prover.set_context(context.get()).unwrap();
+        prover
+            .add_input(&to_vec(&(7u32, "failed")).unwrap())
+            .unwrap();
+        let receipt = prover.run().unwrap();
+        assert_eq!(receipt.verify_with_exit_code(EXIT_ID).unwrap(), 7);
+        assert_eq!(receipt.get_exit_code().unwrap(), 7);
+        assert_ne!(receipt.get_seal().unwrap()[8] & 1 << 29, 0);
+        let journal = receipt.get_journal_vec().unwrap();
+        assert_eq!(journal[..8], *context.get());
+        assert_eq!(from_slice::<&str>(&journal[8..]).unwrap(), "failed");
+    }
+
+    #[test]
+    fn files() {
+        let mut prover = Prover::new(FILES_PATH, FILES_ID).unwrap();
+        prover.add_file("config.json", b"{}").unwrap();
+        prover.add_file("abc", b"abc").unwrap();
+        assert!(prover.add_file("abc", b"").is_err());
+        prover
+            .add_input(&to_vec(&["abc", "config.json"].as_slice()).unwrap())
+            .unwrap();
+        let receipt = prover.run().unwrap();
+        let output = prover.get_output_vec().unwrap();
+        assert_eq!(from_slice::<[usize; 2]>(&output).unwrap(), [3, 2]);
+        let journal = receipt.get_journal_vec().unwrap();
+        let (name, digest): (&str, Digest) = from_slice(&journal).unwrap();
+        assert_eq!(name, "abc");
+        assert_eq!(
+            digest,
+            Digest::new([
+                0xba7816bf, 0x8f01cfea, 0x414140de, 0x5dae2223, 0xb00361a3, 0x96177a9c, 0xb410ff61,
+                0xf20015ad
+            ])
+        );
+    }
+
+    #[test]
+    fn merkle_journal() {
+        let salt = Digest::new([1, 2, 3, 4, 5, 6, 7, 8]);
+        let mut prover = Prover::new(MERKLE_JOURNAL_PATH, MERKLE_JOURNAL_ID).unwrap();
+        prover
+            .add_input(&to_vec(&("public", 42u32, salt)).unwrap())
+            .unwrap();
+        let receipt = prover.run().unwrap();
+        receipt.verify(MERKLE_JOURNAL_ID).unwrap();
+        let journal = receipt.get_journal_vec().unwrap();
+        let public = to_vec(&"public").unwrap();
+        let mut expected = vec![public.len() as u32];
+        expected.extend(public);
+        expected.push(1 | 1 << 31);
+        expected.extend(salt.as_slice());
+        expected.push(42);
+        assert_eq!(journal, expected);
+    }
+
+    #[test]
+    fn guest_tests() {
+        use crate::test_runner::{list_tests, run_test, run_tests};
+
+        let names = list_tests(GUEST_TESTS_PATH, GUEST_TESTS_ID).unwrap();
+        assert_eq!(names, ["digest", "read_write", "memory", "fails"]);
+        assert_eq!(
+            run_test(GUEST_TESTS_PATH, GUEST_TESTS_ID, 0).unwrap(),
+            Ok(())
+        );
+        let failure = run_test(GUEST_TESTS_PATH, GUEST_TESTS_ID, 3)
+            .unwrap()
+            .unwrap_err();
+        assert!(
+            failure.contains("This test is expected to fail"),
+            "{}",
+            failure
+        );
+
+        let results = run_tests(GUEST_TESTS_PATH, GUEST_TESTS_ID).unwrap();
+        let names: Vec<&str> = results.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["digest", "read_write", "memory", "fails"]);
+        for (_, result) in &results[..3] {
+            assert_eq!(*result, Ok(()));
+        }
+        assert!(results[3].1.is_err());
+    }
+
+    #[test]
+    fn cc() {
+        // FNV-1a, computed by C code linked into the guest.
+        assert_eq!(run_cc(b""), 0x811c9dc5);
+        assert_eq!(run_cc(b"a"), 0xe40c292c);
+        assert_eq!(run_cc(b"foobar"), 0xbf9cf968);
+    }
+
+    #[test]
+    fn sig() {
+        // Verifying a signature takes more cycles than an execution may run
+        // for, so each one takes a chain of proofs, each carrying on from the
+        // words the last one committed.
+        let msg = b"hello world";
+        let key = from_hex(
+            "049377c312145a5afb911bf9e8c067bcf6094c533603687850df502b61290bbf5e\
+             742ba607d6ae1fc82dcccb176e7c8f9bdc7bb61e3ab527cecaf6144b679779fb",
+        );
+        let sig = from_hex(
+            "c0a3ece0086ba5db73e7ea9d0127675f58736e1197703209596f6ea4128c585f\
+             68257e27fbe79ec3bf12b9413771a84b239f05c52047bff7af455120d238db0d",
+        );
+        assert_eq!(run_sig("secp256k1", &key, msg, &sig), Ok(()));
+        assert_eq!(
+            run_sig("secp256k1", &key, msg, &sig[..63]),
+            Err("Invalid signature".into())
+        );
+        // The secp256k1 key isn't on P-256.
+        assert_eq!(
+            run_sig("p256", &key, msg, &sig),
+            Err("Invalid public key".into())
+        );
+
+        let key = from_hex(
+            "04f9ebe464147cc102d92324d099d927c1a50e42d57a08a116ffec0a29819d8c65\
+             70baf7d12888902a6a0c1d67add103123c859ab69bf8e9431c9384930d4b7eee",
+        );
+        let sig = from_hex(
+            "085aa28d4dd369a98276cf99f2c5096c7c304fd05312ab0307eea6592feca811\
+             daeba9db138411e33e4f5eb3f8d84e25f48ef88c70fa6ca61c77ad958b46da14",
+        );
+        assert_eq!(run_sig("p256", &key, msg, &sig), Ok(()));
+
+        // Test 1 of RFC 8032.
+        let key = from_hex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a");
+        let sig = from_hex(
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155\
+             5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+        );
+        assert_eq!(run_sig("ed25519", &key, b"", &sig), Ok(()));
+        // The encoded y is more than the field prime.
+        assert_eq!(
+            run_sig("ed25519", &[0xff; 32], b"", &sig),
+            Err("Invalid public key".into())
+        );
+    }
+
+    #[test]
+    fn exit() {
+        assert_eq!(run_exit(0, "ok"), 0);
+        assert_eq!(run_exit(3, ""), 3);
+        assert_eq!(run_exit(7, "invalid input"), 7);
+    }
+
+    #[test]
+    fn execute() {
+        let mut prover = Prover::new(EXIT_PATH, EXIT_ID).unwrap();
+        prover
+            .add_input(&to_vec(&(7u32, "dry run")).unwrap())
+            .unwrap();
+        let result = prover.execute().unwrap();
+        assert_eq!(result.get_exit_code().unwrap(), 7);
+        assert!(result.get_cycles().unwrap() > 0);
+        let journal = result.get_journal_vec().unwrap();
+        assert_eq!(from_slice::<&str>(&journal).unwrap(), "dry run");
+
+        // Proving afterwards gives the same journal, not a second copy of it.
+        let receipt = prover.run().unwrap();
+        assert_eq!(receipt.get_journal_vec().unwrap(), journal);
+        assert_eq!(receipt.verify_with_exit_code(EXIT_ID).unwrap(), 7);
+
+        // A fault is an error, as it is when proving.
+        let prover = Prover::new(FAIL_PATH, FAIL_ID).unwrap();
+        assert!(prover.execute().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "dev-mode")]
+    fn dev_mode_receipt() {
+        let mut prover = Prover::new(EXIT_PATH, EXIT_ID).unwrap();
+        prover.add_input(&to_vec(&(0u32, "dev")).unwrap()).unwrap();
+        let receipt = prover.run_dev_mode().unwrap();
+        assert!(receipt.is_dev_mode().unwrap());
+        let journal = receipt.get_journal_vec().unwrap();
+        assert_eq!(from_slice::<&str>(&journal).unwrap(), "dev");
+
+        // The fake seal is rejected unless dev mode is enabled.
+        assert_eq!(receipt.verify(EXIT_ID).is_ok(), super::dev_mode());
+    }
+
+    fn run_cc(data: &[u8]) -> u32 {
+        let mut prover = Prover::new(CC_PATH, CC_ID).unwrap();
+        let vec = to_vec(&data).unwrap();
+        prover.add_input(vec.as_slice()).unwrap();
+        let receipt = prover.run().unwrap();
+        receipt.verify(CC_ID).unwrap();
+        from_slice(&receipt.get_journal_vec().unwrap()).unwrap()
+    }
+
+    // Proves a chain of executions of the sig method, checking that each
+    // one is for the same signature and starts from the words the last one
+    // ended with, until one commits the result.
+    fn run_sig(
+        scheme: &str,
+        key: &[u8],
+        msg: &[u8],
+        sig: &[u8],
+    ) -> std::result::Result<(), String> {
+        let mut words: Vec<u32> = Vec::new();
+        loop {
+            let mut prover = Prover::new(SIG_PATH, SIG_ID).unwrap();
+            let vec = to_vec(&(scheme, key, msg, sig, &words)).unwrap();
+            prover.add_input(vec.as_slice()).unwrap();
+            let receipt = prover.run().unwrap();
+            receipt.verify(SIG_ID).unwrap();
+            let journal = receipt.get_journal_vec().unwrap();
+            type Journal<'a> = (
+                &'a str,
+                Vec<u8>,
+                Vec<u8>,
+                Vec<u8>,
+                Vec<u32>,
+                std::result::Result<Option<Vec<u32>>, String>,
+            );
+            let (scheme_out, key_out, msg_out, sig_out, words_in, outcome): Journal =
+                from_slice(&journal).unwrap();
+            assert_eq!(
+                (scheme_out, &*key_out, &*msg_out, &*sig_out),
+                (scheme, key, msg, sig)
+            );
+            assert_eq!(words_in, words);
+            match outcome? {
+                Some(next) => words = next,
+                None => return Ok(()),
+            }
+        }
+    }
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn run_exit(code: u32, msg: &str) -> u32 {
+        let mut prover = Prover::new(EXIT_PATH, EXIT_ID).unwrap();
+        let vec = to_vec(&(code, msg)).unwrap();
+        prover.add_input(vec.as_slice()).unwrap();
+        let receipt = prover.run().unwrap();
+        let journal = receipt.get_journal_vec().unwrap();
+        assert_eq!(from_slice::<&str>(journal.as_slice()).unwrap(), msg);
+        assert_eq!(receipt.verify(EXIT_ID).is_ok(), code == 0);
+        assert_eq!(receipt.verify_with_exit_code(EXIT_ID).unwrap(), code);
+        receipt.get_exit_code().unwrap()
+    }
 }
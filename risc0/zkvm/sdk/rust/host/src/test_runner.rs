@@ -0,0 +1,92 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runs tests inside the ZKVM.
+//!
+//! A guest test method is defined with `risc0_zkvm_guest::test_entry!` and
+//! built with `risc0_build::embed_methods` like any other method. A host-side
+//! test then runs all of its tests:
+//!
+//! ```ignore
+//! use risc0_zkvm_host::test_runner::run_tests;
+//!
+//! #[test]
+//! fn guest_tests() {
+//!     let results = run_tests(MY_TESTS_PATH, MY_TESTS_ID).unwrap();
+//!     for (name, result) in results {
+//!         assert_eq!(result, Ok(()), "guest test {}", name);
+//!     }
+//! }
+//! ```
+
+use risc0_zkvm_platform::{LIST_TESTS, TEST_RESULT_CHANNEL};
+use risc0_zkvm_serde::{from_slice, to_vec};
+
+use crate::{into_words, Exception, Prover, Result};
+
+/// The result of a guest test: `Err` holds the reason it failed, such as its
+/// panic message.
+pub type TestResult = std::result::Result<(), String>;
+
+fn prover(elf_path: &str, method_id: &[u8], index: u32) -> Result<Prover> {
+    let mut prover = Prover::new(elf_path, method_id)?;
+    prover.add_input(&to_vec(&index).unwrap())?;
+    Ok(prover)
+}
+
+/// Returns the names of the tests in the given test method.
+pub fn list_tests(elf_path: &str, method_id: &[u8]) -> Result<Vec<String>> {
+    // Listing the tests needs no proof.
+    let prover = prover(elf_path, method_id, LIST_TESTS)?;
+    prover.execute()?;
+    from_slice(&prover.get_output_vec()?)
+        .map_err(|err| Exception::new(&format!("Bad test list from guest: {}", err)))
+}
+
+/// Runs the test with the given index in the given test method, in its own
+/// execution of the ZKVM, verifies its receipt, and returns the result the
+/// guest reported.
+///
+/// A failed test is proven like a passing one, so an error here means the
+/// method couldn't be run or proven at all, such as when it faults.
+pub fn run_test(elf_path: &str, method_id: &[u8], index: u32) -> Result<TestResult> {
+    let prover = prover(elf_path, method_id, index)?;
+    let receipt = prover.run()?;
+    let code = receipt.verify_with_exit_code(method_id)?;
+    let record = into_words(prover.get_channel_output(TEST_RESULT_CHANNEL)?)?;
+    if record.is_empty() {
+        // The test called `env::exit` itself.
+        return Ok(Err(format!("Test exited early with code {}", code)));
+    }
+    from_slice(&record)
+        .map_err(|err| Exception::new(&format!("Bad test result from guest: {}", err)))
+}
+
+/// Runs every test in the given test method, each in its own execution of
+/// the ZKVM, and returns the name and result of each.
+///
+/// A test whose execution can't be run or proven is reported as failed with
+/// the error. Returns an error only if the tests can't be listed.
+pub fn run_tests(elf_path: &str, method_id: &[u8]) -> Result<Vec<(String, TestResult)>> {
+    let names = list_tests(elf_path, method_id)?;
+    Ok(names
+        .into_iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let result = run_test(elf_path, method_id, index as u32)
+                .unwrap_or_else(|err| Err(err.to_string()));
+            (name, result)
+        })
+        .collect())
+}
@@ -0,0 +1,47 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+#![no_std]
+
+use risc0_zkvm_guest::{
+    bigint::{Montgomery, Uint, U256, U384},
+    env,
+};
+
+risc0_zkvm_guest::entry!(main);
+
+const P256: &str = "ffffffff00000001000000000000000000000000ffffffffffffffffffffffff";
+const P384: &str = "fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe\
+                    ffffffff0000000000000000ffffffff";
+
+// Squares a field element `iterations` times. The host counts the cycles
+// taken by comparing executions with different numbers of iterations.
+fn bench<const N: usize>(modulus: &Uint<N>, iterations: u32) {
+    let field = Montgomery::new(modulus);
+    let mut x = field.to_montgomery(&Uint::from_u32(3));
+    for _ in 0..iterations {
+        x = field.mul(&x, &x);
+    }
+    env::commit(&field.from_montgomery(&x).0);
+}
+
+pub fn main() {
+    let (bits, iterations): (u32, u32) = env::read();
+    match bits {
+        256 => bench(&U256::from_be_hex(P256), iterations),
+        384 => bench(&U384::from_be_hex(P384), iterations),
+        _ => panic!("Unsupported size: {}", bits),
+    }
+}
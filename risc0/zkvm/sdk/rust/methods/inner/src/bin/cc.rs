@@ -0,0 +1,33 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use risc0_zkvm_guest::env;
+
+risc0_zkvm_guest::entry!(main);
+
+// Compiled from c/fnv.c by risc0-build.
+extern "C" {
+    fn fnv1a(data: *const u8, len: usize) -> u32;
+}
+
+pub fn main() {
+    let data: Vec<u8> = env::read();
+    env::commit(&unsafe { fnv1a(data.as_ptr(), data.len()) });
+}
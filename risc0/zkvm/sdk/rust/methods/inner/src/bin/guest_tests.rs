@@ -0,0 +1,73 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+#![no_std]
+
+use risc0_zkp_core::sha::Digest;
+use risc0_zkvm_guest::{env, sha};
+
+risc0_zkvm_guest::test_entry!(digest, read_write, memory, fails);
+
+fn digest() {
+    assert_eq!(
+        sha::digest_u8_slice("abc".as_bytes()),
+        Digest::new([
+            0xba7816bf, 0x8f01cfea, 0x414140de, 0x5dae2223, 0xb00361a3, 0x96177a9c, 0xb410ff61,
+            0xf20015ad
+        ])
+    );
+}
+
+fn read_write() {
+    env::write(&"output");
+    assert!(env::try_read::<u32>().is_err());
+}
+
+// Exercises memcpy, memmove, memset and memcmp at each alignment.
+fn memory() {
+    let mut src = [0u8; 100];
+    for (i, byte) in src.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    for offset in 0..4 {
+        for len in [0, 1, 7, 8, 33, 64, 90] {
+            let mut dst = [0xffu8; 100];
+            dst[offset..offset + len].copy_from_slice(&src[3..3 + len]);
+            for i in 0..len {
+                assert_eq!(dst[offset + i], (3 + i) as u8);
+            }
+            assert!(dst[offset + len..].iter().all(|byte| *byte == 0xff));
+            assert_eq!(dst[offset..offset + len], src[3..3 + len]);
+            if len > 0 {
+                dst[offset + len - 1] += 1;
+                assert!(dst[offset..offset + len] > src[3..3 + len]);
+            }
+
+            let mut buf = src;
+            buf.copy_within(offset..offset + len, 5);
+            for i in 0..len {
+                assert_eq!(buf[5 + i], (offset + i) as u8);
+            }
+
+            dst.fill(0);
+            dst[offset..offset + len].fill(0xa5);
+            assert_eq!(dst.iter().filter(|byte| **byte == 0xa5).count(), len);
+        }
+    }
+}
+
+fn fails() {
+    panic!("This test is expected to fail");
+}
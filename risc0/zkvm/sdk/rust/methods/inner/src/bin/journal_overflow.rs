@@ -0,0 +1,43 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use risc0_zkvm_guest::env;
+
+risc0_zkvm_guest::entry!(main);
+
+// Commits the words 0 to count - 1 one at a time, then as one slice, keeping
+// only the first `capacity` words of the journal in the commit region, or
+// all that fit if `capacity` is 0. Then commits a slice read from the host
+// twice.
+pub fn main() {
+    let (capacity, count): (u32, u32) = env::read();
+    if capacity != 0 {
+        env::set_commit_capacity(capacity as usize);
+    }
+    for i in 0..count {
+        env::commit(&i);
+    }
+    let words: Vec<u32> = (0..count).collect();
+    env::commit_slice(&words);
+    let input: &[u32] = env::read_slice();
+    env::commit_slice(input);
+    env::commit_slice(input);
+}
@@ -0,0 +1,61 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::String, vec, vec::Vec};
+use core::ptr;
+
+use risc0_zkvm_guest::env;
+
+risc0_zkvm_guest::entry!(main);
+
+const SIZE: usize = 4096;
+
+// Copies a byte at a time, as the generic memcpy does. The volatile writes
+// keep this from being turned into a call to memcpy.
+fn copy_bytes(dst: &mut [u8], src: &[u8]) {
+    for (d, s) in dst.iter_mut().zip(src) {
+        unsafe { ptr::write_volatile(d, *s) };
+    }
+}
+
+// Runs the operation named by the host. The host counts the cycles it takes
+// by comparing with an execution which runs none.
+pub fn main() {
+    let op: &str = env::read();
+    let src = vec![0x5a_u8; SIZE + 4];
+    let mut dst = vec![0_u8; SIZE + 4];
+    match op {
+        "none" => {}
+        "memcpy" => dst[..SIZE].copy_from_slice(&src[..SIZE]),
+        "memcpy unaligned" => dst[..SIZE].copy_from_slice(&src[1..SIZE + 1]),
+        "byte loop" => copy_bytes(&mut dst[..SIZE], &src[..SIZE]),
+        "memset" => dst[..SIZE].fill(0),
+        // Equal slices are compared to the end.
+        "memcmp" => assert!(dst[..SIZE] == dst[4..SIZE + 4]),
+        // Deserializing strings copies them out of the input, and committing
+        // them copies them into the journal.
+        "serde" => {
+            let strings: Vec<String> = env::read();
+            env::commit(&strings);
+        }
+        _ => panic!("Unknown operation: {}", op),
+    }
+    // Keeps the operations on dst from being optimized away.
+    env::write_slice(&dst);
+}
@@ -0,0 +1,38 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+#![no_std]
+
+use core::ptr;
+
+use risc0_zkvm_guest::env;
+
+risc0_zkvm_guest::entry!(main);
+
+// Adds up `depth` and every number below it, using a stack frame of a
+// little over 256 bytes per level.
+fn sum(depth: u32) -> u32 {
+    let mut frame = [0u32; 64];
+    unsafe { ptr::write_volatile(&mut frame[depth as usize % 64], depth) };
+    if depth == 0 {
+        return 0;
+    }
+    sum(depth - 1) + unsafe { ptr::read_volatile(&frame[depth as usize % 64]) }
+}
+
+pub fn main() {
+    let depth: u32 = env::read();
+    env::commit(&sum(depth));
+}
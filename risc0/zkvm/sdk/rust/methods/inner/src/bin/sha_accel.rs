@@ -15,6 +15,7 @@
 #![no_main]
 #![no_std]
 
+use digest::Digest;
 use risc0_zkvm_guest::{env, sha};
 
 risc0_zkvm_guest::entry!(main);
@@ -24,5 +25,16 @@ pub fn main() {
     let digest = sha::digest_u8_slice(data);
     env::commit(&digest);
 
-    risc0_zkp_core::sha::testutil::test_sha_impl(&risc0_zkvm_guest::sha::Impl {})
+    // The streaming hasher must agree with the one-shot digest, no matter how
+    // the input is split up.
+    let mut hasher = sha::Sha256::new();
+    for chunk in data.chunks(7) {
+        hasher.update(chunk);
+    }
+    let output = hasher.finalize();
+    for (chunk, word) in output.chunks_exact(4).zip(digest.get()) {
+        assert_eq!(chunk, word.to_be_bytes());
+    }
+
+    risc0_zkp_core::sha::testutil::test_sha_impl(&risc0_zkvm_guest::sha::AcceleratedImpl {})
 }
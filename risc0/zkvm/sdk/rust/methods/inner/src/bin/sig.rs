@@ -0,0 +1,84 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::ToString, vec::Vec};
+
+use risc0_zkvm_guest::{
+    env,
+    sig::{ecdsa, ed25519, Error},
+};
+
+risc0_zkvm_guest::entry!(main);
+
+// Field multiplications done per execution. The costliest run of pieces
+// within this takes about 250k instructions, which leaves room under the
+// cycle limit for reading and committing the words.
+const BUDGET: u32 = 100;
+
+// Does one execution's worth of verifying a signature with the scheme named
+// by the host. With no words, the signature is only checked and the words to
+// start from are made. Commits the inputs and the words started from, with
+// the words to carry on from, or the result once it is known.
+pub fn main() {
+    let (scheme, public_key, msg, sig, words): (&str, Vec<u8>, Vec<u8>, Vec<u8>, Vec<u32>) =
+        env::read();
+    let outcome = match scheme {
+        "secp256k1" => step_ecdsa(&ecdsa::Curve::secp256k1(), &public_key, &msg, &sig, &words),
+        "p256" => step_ecdsa(&ecdsa::Curve::p256(), &public_key, &msg, &sig, &words),
+        "ed25519" => step_ed25519(&public_key, &msg, &sig, &words),
+        _ => panic!("Unknown scheme: {}", scheme),
+    };
+    let outcome = outcome.map_err(|err| err.to_string());
+    env::commit(&(scheme, public_key, msg, sig, words, outcome));
+}
+
+fn step_ecdsa(
+    curve: &ecdsa::Curve,
+    public_key: &[u8],
+    msg: &[u8],
+    sig: &[u8],
+    words: &[u32],
+) -> Result<Option<Vec<u32>>, Error> {
+    if words.is_empty() {
+        let verifier = ecdsa::Verifier::new(curve, public_key, &ecdsa::digest(msg), sig)?;
+        return Ok(Some(verifier.to_words()));
+    }
+    let mut verifier = ecdsa::Verifier::from_words(words).expect("Malformed words");
+    match verifier.run(curve, BUDGET) {
+        Some(result) => result.map(|()| None),
+        None => Ok(Some(verifier.to_words())),
+    }
+}
+
+fn step_ed25519(
+    public_key: &[u8],
+    msg: &[u8],
+    sig: &[u8],
+    words: &[u32],
+) -> Result<Option<Vec<u32>>, Error> {
+    if words.is_empty() {
+        let verifier = ed25519::Verifier::new(public_key, msg, sig)?;
+        return Ok(Some(verifier.to_words()));
+    }
+    let mut verifier = ed25519::Verifier::from_words(words).expect("Malformed words");
+    match verifier.run(BUDGET) {
+        Some(result) => result.map(|()| None),
+        None => Ok(Some(verifier.to_words())),
+    }
+}
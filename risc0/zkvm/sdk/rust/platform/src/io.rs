@@ -0,0 +1,105 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::memory::GPIO;
+
+/// Starts a batch of SHA-256 hashes, given a [ShaDescriptor].
+pub const GPIO_SHA: usize = 0x001C_0000;
+
+/// Sends private output to the host, given an [IoDescriptor].
+pub const GPIO_WRITE: usize = 0x001C_0004;
+
+/// Sends part of the journal to the host, given an [IoDescriptor].
+pub const GPIO_COMMIT: usize = 0x001C_0008;
+
+/// Fails the execution, given a [FaultDescriptor].
+pub const GPIO_FAULT: usize = 0x001C_000C;
+
+/// Prints a message to the host's log, given a [LogDescriptor].
+pub const GPIO_LOG: usize = 0x001C_0010;
+
+/// Fetches a key from the host's key store, given a [GetKeyDescriptor].
+pub const GPIO_GETKEY: usize = 0x001C_0014;
+
+/// Sends private output to the host on a numbered channel, given a
+/// [ChannelDescriptor].
+pub const GPIO_WRITE_CHANNEL: usize = 0x001C_0018;
+
+/// Where the guest builds a descriptor before writing its address to a GPIO.
+pub const GPIO_DESC: usize = 0x001D_0000;
+
+// The GPIOs and the descriptor must all be in the GPIO region.
+const _: () = {
+    assert!(GPIO.contains(GPIO_SHA) && GPIO.contains(GPIO_WRITE_CHANNEL));
+    assert!(GPIO.contains(GPIO_DESC) && GPIO_DESC > GPIO_WRITE_CHANNEL);
+};
+
+/// Describes a buffer of guest memory.
+#[repr(C)]
+pub struct IoDescriptor {
+    /// The size of the buffer in bytes.
+    pub size: u32,
+    /// The address of the buffer.
+    pub addr: u32,
+}
+
+/// Describes a buffer of guest memory to send on an output channel.
+#[repr(C)]
+pub struct ChannelDescriptor {
+    /// The channel to send the buffer on.
+    pub channel: u32,
+    /// The size of the buffer in bytes.
+    pub size: u32,
+    /// The address of the buffer.
+    pub addr: u32,
+}
+
+/// Describes why the guest failed.
+#[repr(C)]
+pub struct FaultDescriptor {
+    /// The address of a NUL-terminated message.
+    pub addr: u32,
+}
+
+/// Describes a message to log.
+#[repr(C)]
+pub struct LogDescriptor {
+    /// The address of a NUL-terminated message.
+    pub addr: u32,
+}
+
+/// Describes a key to fetch from the host's key store.
+#[repr(C)]
+pub struct GetKeyDescriptor {
+    /// The address of the NUL-terminated name of the key.
+    pub name: u32,
+    /// The address to write the key to.
+    pub addr: u32,
+    /// 0 if the key must not exist yet, 1 if it must already exist, or any
+    /// other value to accept either.
+    pub mode: u32,
+}
+
+/// Describes a request to the SHA accelerator.
+#[repr(C)]
+pub struct ShaDescriptor {
+    /// The number of 64-byte blocks to hash.
+    pub type_count: u32,
+    /// Only logged by the host.
+    pub idx: u32,
+    /// The address of the blocks to hash.
+    pub source: u32,
+    /// The address to write the digest to.
+    pub digest: u32,
+}
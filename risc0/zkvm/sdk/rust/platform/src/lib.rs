@@ -0,0 +1,39 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The memory map and I/O interface of the ZKVM, shared by the guest, the
+//! host and the build tools.
+//!
+//! `risc0/zkvm/platform/memory.h`, `io.h` and `risc0.ld` must match these
+//! definitions, which is checked by the tests of `risc0-zkvm-platform-sys`.
+
+#![no_std]
+#![deny(missing_docs)]
+
+/// Addresses and descriptors of the GPIOs a guest uses to talk to the host.
+pub mod io;
+
+/// The regions of guest memory.
+pub mod memory;
+
+/// The size of a word in the ZKVM, in bytes.
+pub const WORD_SIZE: usize = core::mem::size_of::<u32>();
+
+/// Input which asks a test method defined with `risc0_zkvm_guest::test_entry!`
+/// for the names of its tests, rather than running one of them.
+pub const LIST_TESTS: u32 = u32::MAX;
+
+/// The output channel on which a test method reports the result of the test
+/// it ran, apart from any output of the test itself.
+pub const TEST_RESULT_CHANNEL: u32 = 1;
@@ -0,0 +1,389 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::fmt;
+
+/// A region of memory, as its start address and length in bytes.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Region {
+    start: usize,
+    len: usize,
+}
+
+impl Region {
+    /// Creates a region from its start address and length in bytes.
+    pub const fn new(start: usize, len: usize) -> Self {
+        Region { start, len }
+    }
+
+    /// Returns the first address of the region.
+    pub const fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the length of the region in bytes.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the address just past the end of the region.
+    pub const fn end(&self) -> usize {
+        self.start + self.len
+    }
+
+    /// Returns true if the region contains the given address.
+    pub const fn contains(&self, addr: usize) -> bool {
+        self.start <= addr && addr < self.end()
+    }
+
+    /// Returns true if the two regions share any addresses.
+    pub const fn overlaps(&self, other: &Region) -> bool {
+        self.start < other.end() && other.start < self.end()
+    }
+}
+
+const KB: usize = 1024;
+const MB: usize = 1024 * KB;
+
+/// The number of bits in a word address.
+pub const MEM_BITS: usize = 20;
+
+/// The size of guest memory in bytes.
+pub const MEM_SIZE: usize = (1 << MEM_BITS) * 4;
+
+/// The stack, which starts at the end of the region and grows down.
+pub const STACK: Region = Region::new(0x0000_0000, 256 * KB);
+
+/// Initialized and zeroed data.
+pub const DATA: Region = Region::new(0x0004_0000, 256 * KB);
+
+/// The heap.
+pub const HEAP: Region = Region::new(0x0008_0000, MB);
+
+/// Input from the host, which the guest reads.
+pub const INPUT: Region = Region::new(0x0018_0000, 256 * KB);
+
+/// The GPIOs and their descriptors; see [crate::io].
+pub const GPIO: Region = Region::new(0x001C_0000, 256 * KB);
+
+/// The program.
+pub const PROG: Region = Region::new(0x0020_0000, MB);
+
+/// Descriptors for the SHA accelerator.
+pub const SHA: Region = Region::new(0x0030_0000, 256 * KB);
+
+/// Write-once memory, which holds [OUTPUT] and [COMMIT].
+pub const WOM: Region = Region::new(0x0034_0000, 512 * KB);
+
+/// Private output to the host.
+pub const OUTPUT: Region = Region::new(0x0034_0000, 256 * KB);
+
+/// The journal, which is committed to in the receipt.
+pub const COMMIT: Region = Region::new(0x0038_0000, 256 * KB);
+
+/// The top-level regions, in address order.
+pub const REGIONS: [Region; 8] = [STACK, DATA, HEAP, INPUT, GPIO, PROG, SHA, WOM];
+
+// The top-level regions must be in order, must not overlap, and must fit in
+// memory, and the output and the journal must share the write-once memory.
+const _: () = {
+    let mut i = 0;
+    while i < REGIONS.len() {
+        assert!(REGIONS[i].start % 4 == 0 && REGIONS[i].len % 4 == 0);
+        if i > 0 {
+            assert!(
+                REGIONS[i - 1].end() <= REGIONS[i].start,
+                "Memory regions overlap"
+            );
+        }
+        i += 1;
+    }
+    assert!(WOM.end() <= MEM_SIZE, "Memory regions don't fit in memory");
+    assert!(OUTPUT.start == WOM.start && COMMIT.end() <= WOM.end());
+    assert!(!OUTPUT.overlaps(&COMMIT));
+};
+
+/// The granularity of the region sizes in a [Layout].
+pub const LAYOUT_ALIGN: usize = 4 * KB;
+
+/// The sizes of the regions a method can choose for itself; see [Layout].
+///
+/// The stack, data, heap and input share the 1.75 MB below [GPIO], so a
+/// bigger heap needs a smaller stack or input. The output and the journal
+/// share the 768 KB at the top of memory, and the journal is always 256 KB.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Sizes {
+    /// The size of the stack in bytes.
+    pub stack: usize,
+
+    /// The size of the heap in bytes.
+    pub heap: usize,
+
+    /// The size of the input region in bytes.
+    pub input: usize,
+
+    /// The size of the output region in bytes.
+    pub output: usize,
+}
+
+impl Sizes {
+    /// The sizes of the default memory map.
+    pub const DEFAULT: Sizes = Sizes {
+        stack: STACK.len,
+        heap: HEAP.len,
+        input: INPUT.len,
+        output: OUTPUT.len,
+    };
+}
+
+impl Default for Sizes {
+    fn default() -> Self {
+        Sizes::DEFAULT
+    }
+}
+
+/// The placement of the regions whose sizes a method can choose.
+///
+/// The linker script places the layout a method was built with at the start
+/// of [PROG], where the guest finds it as `__layout` and the host reads it
+/// to place the input. Since it is part of the program image, it is also
+/// covered by the method ID.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Layout {
+    magic: u32,
+
+    /// The stack, which starts at the end of the region and grows down.
+    pub stack: Region,
+
+    /// Initialized and zeroed data.
+    pub data: Region,
+
+    /// The heap.
+    pub heap: Region,
+
+    /// Input from the host.
+    pub input: Region,
+
+    /// Private output to the host.
+    pub output: Region,
+
+    /// The journal.
+    pub commit: Region,
+}
+
+impl Layout {
+    /// The first word of a layout in the program image.
+    pub const MAGIC: u32 = u32::from_le_bytes(*b"R0LY");
+
+    /// The default memory map.
+    pub const DEFAULT: Layout = Layout {
+        magic: Layout::MAGIC,
+        stack: STACK,
+        data: DATA,
+        heap: HEAP,
+        input: INPUT,
+        output: OUTPUT,
+        commit: COMMIT,
+    };
+
+    /// Lays out regions of the given sizes.
+    ///
+    /// The stack, data, heap and input are placed in that order from address
+    /// 0, and the output and the journal from the start of [WOM].
+    pub fn new(sizes: Sizes) -> Result<Layout, LayoutError> {
+        for (region, size) in [
+            ("stack", sizes.stack),
+            ("heap", sizes.heap),
+            ("input", sizes.input),
+            ("output", sizes.output),
+        ] {
+            if size == 0 || size % LAYOUT_ALIGN != 0 || size > MEM_SIZE {
+                return Err(LayoutError::Size { region, size });
+            }
+        }
+
+        let stack = Region::new(STACK.start, sizes.stack);
+        let data = Region::new(stack.end(), DATA.len);
+        let heap = Region::new(data.end(), sizes.heap);
+        let input = Region::new(heap.end(), sizes.input);
+        if input.end() > GPIO.start {
+            return Err(LayoutError::Ram {
+                size: input.end(),
+                available: GPIO.start,
+            });
+        }
+
+        let output = Region::new(WOM.start, sizes.output);
+        let commit = Region::new(output.end(), COMMIT.len);
+        if commit.end() > MEM_SIZE {
+            return Err(LayoutError::Wom {
+                size: commit.end() - WOM.start,
+                available: MEM_SIZE - WOM.start,
+            });
+        }
+
+        Ok(Layout {
+            magic: Layout::MAGIC,
+            stack,
+            data,
+            heap,
+            input,
+            output,
+            commit,
+        })
+    }
+
+    /// Returns the sizes this layout was made from.
+    pub fn sizes(&self) -> Sizes {
+        Sizes {
+            stack: self.stack.len,
+            heap: self.heap.len,
+            input: self.input.len,
+            output: self.output.len,
+        }
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout::DEFAULT
+    }
+}
+
+/// The reasons a [Layout] can't be made from a set of [Sizes].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LayoutError {
+    /// A region is empty, too big, or not a multiple of [LAYOUT_ALIGN].
+    Size {
+        /// The name of the region.
+        region: &'static str,
+        /// The requested size.
+        size: usize,
+    },
+
+    /// The stack, data, heap and input don't fit below [GPIO].
+    Ram {
+        /// The total size of the regions.
+        size: usize,
+        /// The space available for them.
+        available: usize,
+    },
+
+    /// The output and the journal don't fit in the top of memory.
+    Wom {
+        /// The total size of the regions.
+        size: usize,
+        /// The space available for them.
+        available: usize,
+    },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LayoutError::Size { region, size } => write!(
+                f,
+                "the {} size ({:#x}) must be a non-zero multiple of {:#x}",
+                region, size, LAYOUT_ALIGN
+            ),
+            LayoutError::Ram { size, available } => write!(
+                f,
+                "the stack, data, heap and input ({:#x} bytes) exceed the {:#x} bytes available",
+                size, available
+            ),
+            LayoutError::Wom { size, available } => write!(
+                f,
+                "the output and journal ({:#x} bytes) exceed the {:#x} bytes available",
+                size, available
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Layout, LayoutError, Region, Sizes, COMMIT, HEAP, INPUT, MB, OUTPUT, WOM};
+
+    #[test]
+    fn region() {
+        let region = Region::new(0x100, 0x10);
+        assert_eq!(region.end(), 0x110);
+        assert!(region.contains(0x100));
+        assert!(region.contains(0x10F));
+        assert!(!region.contains(0x110));
+        assert!(!region.contains(0xFF));
+        assert!(region.overlaps(&Region::new(0x10C, 0x10)));
+        assert!(!region.overlaps(&Region::new(0x110, 0x10)));
+    }
+
+    #[test]
+    fn layout() {
+        assert!(!HEAP.overlaps(&INPUT));
+        assert!(WOM.contains(OUTPUT.start()) && WOM.contains(COMMIT.end() - 1));
+    }
+
+    #[test]
+    fn default_layout() {
+        assert_eq!(Layout::new(Sizes::DEFAULT), Ok(Layout::DEFAULT));
+        assert_eq!(Layout::DEFAULT.sizes(), Sizes::DEFAULT);
+    }
+
+    #[test]
+    fn custom_layout() {
+        let sizes = Sizes {
+            stack: 64 * 1024,
+            heap: MB + 384 * 1024,
+            input: 64 * 1024,
+            output: 512 * 1024,
+        };
+        let layout = Layout::new(sizes).unwrap();
+        assert_eq!(layout.sizes(), sizes);
+        assert_eq!(layout.heap, Region::new(0x0005_0000, 0x0016_0000));
+        assert_eq!(layout.input, Region::new(0x001B_0000, 0x0001_0000));
+        assert_eq!(layout.commit, Region::new(0x003C_0000, 0x0004_0000));
+    }
+
+    #[test]
+    fn bad_layout() {
+        let sizes = Sizes {
+            heap: 2 * MB,
+            ..Sizes::DEFAULT
+        };
+        assert_eq!(
+            Layout::new(sizes),
+            Err(LayoutError::Ram {
+                size: 0x002C_0000,
+                available: 0x001C_0000
+            })
+        );
+        let sizes = Sizes {
+            output: 768 * 1024,
+            ..Sizes::DEFAULT
+        };
+        assert!(matches!(Layout::new(sizes), Err(LayoutError::Wom { .. })));
+        let sizes = Sizes {
+            stack: 1000,
+            ..Sizes::DEFAULT
+        };
+        assert_eq!(
+            Layout::new(sizes),
+            Err(LayoutError::Size {
+                region: "stack",
+                size: 1000
+            })
+        );
+    }
+}
@@ -24,6 +24,7 @@ pub fn from_slice<'a, T: Deserialize<'a>>(slice: &'a [u32]) -> Result<T> {
     T::deserialize(&mut deserializer)
 }
 
+#[derive(Clone)]
 pub struct Deserializer<'de> {
     slice: &'de [u32],
 }
@@ -125,6 +126,11 @@ impl<'de> Deserializer<'de> {
         Deserializer { slice }
     }
 
+    /// Returns the words which haven't been deserialized yet.
+    pub fn remaining(&self) -> &'de [u32] {
+        self.slice
+    }
+
     fn try_take_word(&mut self) -> Result<u32> {
         if self.slice.len() >= 1 {
             let (head, tail) = self.slice.split_first().unwrap();
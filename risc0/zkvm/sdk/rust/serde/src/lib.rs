@@ -22,7 +22,11 @@ mod round_trip;
 mod serializer;
 
 pub use deserializer::{from_slice, Deserializer};
-pub use serializer::{to_slice, to_vec, to_vec_with_capacity, AllocVec, Serializer, Slice};
+pub use err::{Error, Result};
+pub use serializer::{
+    serialized_len, to_slice, to_vec, to_vec_with_capacity, AllocVec, Serializer, Slice,
+    StreamWriter,
+};
 
 /// Align the given address `addr` upwards to alignment `align`.
 ///
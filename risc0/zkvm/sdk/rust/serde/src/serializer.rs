@@ -54,6 +54,15 @@ where
     serializer.stream.release()
 }
 
+pub fn serialized_len<T>(value: &T) -> Result<usize>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::new(Counter(0));
+    value.serialize(&mut serializer)?;
+    serializer.stream.release()
+}
+
 pub trait StreamWriter {
     type Output;
 
@@ -81,6 +90,12 @@ impl<W: StreamWriter> Serializer<W> {
     pub fn release(&mut self) -> Result<W::Output> {
         self.stream.release()
     }
+
+    /// Writes the given bytes as they are, without a length, padded with
+    /// zeros to a whole number of words.
+    pub fn try_extend(&mut self, data: &[u8]) -> Result<()> {
+        self.stream.try_extend(data)
+    }
 }
 
 impl<'a, W: StreamWriter> serde::ser::Serializer for &'a mut Serializer<W> {
@@ -494,6 +509,27 @@ impl StreamWriter for AllocVec {
     }
 }
 
+// Counts the words written without storing them.
+struct Counter(usize);
+
+impl StreamWriter for Counter {
+    type Output = usize;
+
+    fn try_push_word(&mut self, _data: u32) -> Result<()> {
+        self.0 += 1;
+        Ok(())
+    }
+
+    fn try_extend(&mut self, data: &[u8]) -> Result<()> {
+        self.0 += align_up(data.len(), WORD_SIZE) / WORD_SIZE;
+        Ok(())
+    }
+
+    fn release(&mut self) -> Result<Self::Output> {
+        Ok(mem::take(&mut self.0))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -560,4 +596,15 @@ mod test {
         let buf: &mut [u32] = &mut [0; 256];
         assert_eq!(expected, to_slice(&input, buf).unwrap());
     }
+
+    #[test]
+    fn test_serialized_len() {
+        let input = (7_u8, "abcde", [1_u64, 2]);
+        let len = serialized_len(&input).unwrap();
+        assert_eq!(len, to_vec(&input).unwrap().len());
+        assert_eq!(len, 8);
+
+        let buf: &mut [u32] = &mut [0; 7];
+        assert_eq!(Err(Error::SerializeBufferFull), to_slice(&input, buf));
+    }
 }
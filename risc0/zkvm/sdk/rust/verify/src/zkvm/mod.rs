@@ -22,7 +22,10 @@ use alloc::{vec, vec::Vec};
 use serde::{Deserialize, Serialize};
 
 use crate::zkp::verify::verify;
-use risc0_zkp_core::sha::{Digest, Sha};
+use risc0_zkp_core::{
+    merkle::{MerkleProof, MerkleTree},
+    sha::{Digest, Sha, DIGEST_WORDS},
+};
 
 pub use crate::zkvm::circuit::MethodID;
 use crate::zkvm::circuit::Risc0Circuit;
@@ -33,12 +36,204 @@ pub struct Receipt {
     seal: Vec<u32>,
 }
 
+// Set in the last output register, along with the exit code, when the guest
+// exits with a failure. The other output registers then hold the digest of
+// the journal.
+const EXIT_FAILURE: u32 = 1 << 31;
+
+// Set in the last output register, along with the journal length or exit
+// code, when the journal is committed as a Merkle tree. Unless the method
+// failed, the other output registers then hold the Merkle root of the journal
+// entries.
+const MERKLE_JOURNAL: u32 = 1 << 30;
+
+// Set in the last output register, along with the journal length or exit
+// code, when the journal starts with the context the host supplied.
+const JOURNAL_CONTEXT: u32 = 1 << 29;
+
+// Set in the header of a journal entry which starts with a salt.
+const ENTRY_SALTED: u32 = 1 << 31;
+
+// Marks the seal of a dev-mode receipt, where a real seal has its po2 after
+// the output registers. Such a seal is fake and proves nothing.
+const DEV_MODE_SEAL: u32 = 0x5644_3052;
+
+/// An entry of a journal committed as a Merkle tree, which is one commit made
+/// by the method.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct JournalEntry {
+    /// The salt which hides the data from anyone who isn't shown the entry.
+    pub salt: Option<Digest>,
+
+    /// The serialized data which was committed.
+    pub data: Vec<u32>,
+}
+
+impl JournalEntry {
+    /// Returns the words of the entry, as they appear in the journal.
+    pub fn to_words(&self) -> Vec<u32> {
+        let mut words = Vec::with_capacity(1 + DIGEST_WORDS + self.data.len());
+        match &self.salt {
+            Some(salt) => {
+                words.push(self.data.len() as u32 | ENTRY_SALTED);
+                words.extend_from_slice(salt.as_slice());
+            }
+            None => words.push(self.data.len() as u32),
+        }
+        words.extend_from_slice(&self.data);
+        words
+    }
+
+    /// Returns the leaf of the Merkle tree for this entry.
+    pub fn leaf(&self) -> Digest {
+        let sha = risc0_zkp_core::sha::default_implementation();
+        *sha.hash_words(&self.to_words())
+    }
+}
+
+/// An entry revealed by a [Disclosure].
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DisclosedEntry {
+    /// The position of the entry in the journal.
+    pub index: u32,
+
+    /// The entry.
+    pub entry: JournalEntry,
+
+    /// The proof that the entry is at `index` in the journal.
+    pub proof: MerkleProof,
+}
+
+/// A receipt for a method which committed its journal as a Merkle tree,
+/// revealing only some of the journal entries.
+#[derive(Deserialize, Serialize)]
+pub struct Disclosure {
+    seal: Vec<u32>,
+    // The row size of the tree, which the seal doesn't record. A leaf is the
+    // SHA-256 digest of an entry, with padding, so it can't pass for an
+    // interior node even in a tree of the wrong size.
+    row_size: u32,
+    entries: Vec<DisclosedEntry>,
+}
+
+impl Disclosure {
+    /// Verifies the seal and the revealed entries, panicking if any of them
+    /// are invalid, and returns the revealed entries.
+    pub fn verify(&self, method_id: &MethodID) -> &[DisclosedEntry] {
+        assert!(!is_dev_mode_seal(&self.seal), "Dev-mode seal rejected");
+        let mut circuit = Risc0Circuit::new(method_id);
+        let sha = risc0_zkp_core::sha::default_implementation();
+        verify(sha, &mut circuit, &self.seal).unwrap();
+        self.verify_entries();
+        &self.entries
+    }
+
+    fn verify_entries(&self) {
+        let sha = risc0_zkp_core::sha::default_implementation();
+        assert!(self.seal[8] & (EXIT_FAILURE | MERKLE_JOURNAL) == MERKLE_JOURNAL);
+        let root = Digest::from_slice(&self.seal[0..8]);
+        for entry in self.entries.iter() {
+            let leaf = entry.entry.leaf();
+            assert!(entry.proof.verify(
+                sha,
+                &root,
+                self.row_size as usize,
+                entry.index as usize,
+                &leaf
+            ));
+        }
+    }
+}
+
+fn is_dev_mode_seal(seal: &[u32]) -> bool {
+    seal.len() == 10 && seal[9] == DEV_MODE_SEAL
+}
+
 impl Receipt {
+    /// Verifies the receipt of a method which halted normally, panicking if
+    /// it is invalid or is for a failed execution.
     pub fn verify(&self, method_id: &MethodID) {
+        assert!(self.verify_with_exit_code(method_id) == 0);
+    }
+
+    /// Verifies the receipt of a method which halted normally, as
+    /// [Receipt::verify] does, and checks that it was made for `context`,
+    /// panicking if not.
+    ///
+    /// The host supplies the context, such as a nonce or the digest of a
+    /// request, and the guest commits it ahead of anything else, so a receipt
+    /// made for one request can't be replayed for another. The journal then
+    /// starts with the 8 words of the context, or with an unsalted entry
+    /// holding them if it is committed as a Merkle tree, and the seal marks
+    /// it as doing so, which a journal the method committed itself can't fake.
+    pub fn verify_with_context(&self, method_id: &MethodID, context: &Digest) {
+        assert!(self.verify_with_context_and_exit_code(method_id, context) == 0);
+    }
+
+    /// Verifies the receipt, which may be for a failed execution, and checks
+    /// that it was made for `context`, panicking if not. Returns the exit
+    /// code, which is 0 if the method halted normally.
+    ///
+    /// A method which fails still commits the context ahead of anything
+    /// else, so a failure can't be replayed for another request either.
+    pub fn verify_with_context_and_exit_code(&self, method_id: &MethodID, context: &Digest) -> u32 {
+        let code = self.verify_with_exit_code(method_id);
+        assert!(self.get_context().as_ref() == Some(context));
+        code
+    }
+
+    /// Verifies the receipt, which may be for a failed execution, panicking
+    /// if it is invalid. Returns the exit code, which is 0 if the method
+    /// halted normally.
+    ///
+    /// Dev-mode receipts, whose seal is fake, are always rejected.
+    pub fn verify_with_exit_code(&self, method_id: &MethodID) -> u32 {
+        assert!(!self.is_dev_mode(), "Dev-mode receipt rejected");
         let mut circuit = Risc0Circuit::new(method_id);
         let sha = risc0_zkp_core::sha::default_implementation();
         verify(sha, &mut circuit, &self.seal).unwrap();
-        assert!(self.journal.len() == (self.seal[8] as usize));
+        self.verify_journal()
+    }
+
+    /// INSECURE: verifies the receipt as [Receipt::verify_with_exit_code]
+    /// does, but also accepts a dev-mode receipt, checking only that its
+    /// journal matches its fake seal. Returns the exit code.
+    ///
+    /// A dev-mode receipt is made without proving the method, so it attests
+    /// to nothing. This is only meant for tests, and needs the `dev-mode`
+    /// feature.
+    #[cfg(feature = "dev-mode")]
+    pub fn verify_allowing_dev_mode(&self, method_id: &MethodID) -> u32 {
+        match self.is_dev_mode() {
+            true => self.verify_journal(),
+            false => self.verify_with_exit_code(method_id),
+        }
+    }
+
+    /// Returns whether this is a dev-mode receipt, made without proving the
+    /// method, whose seal is fake.
+    pub fn is_dev_mode(&self) -> bool {
+        is_dev_mode_seal(&self.seal)
+    }
+
+    // Checks the journal against the output registers at the start of the
+    // seal, and returns the exit code.
+    fn verify_journal(&self) -> u32 {
+        let sha = risc0_zkp_core::sha::default_implementation();
+        let code = self.get_exit_code();
+        if code != 0 {
+            // A failed execution always hashes the journal.
+            let digest = sha.hash_bytes(&self.journal);
+            assert!(*digest == Digest::from_slice(&self.seal[0..8]));
+            return code;
+        }
+        let len = self.seal[8] & !(MERKLE_JOURNAL | JOURNAL_CONTEXT);
+        assert!(self.journal.len() == len as usize);
+        if self.seal[8] & MERKLE_JOURNAL != 0 {
+            let root = Digest::from_slice(&self.seal[0..8]);
+            assert!(*self.merkle_tree().root() == root);
+            return 0;
+        }
         if self.journal.len() > 32 {
             let digest = sha.hash_bytes(&self.journal);
             assert!(*digest == Digest::from_slice(&self.seal[0..8]));
@@ -51,6 +246,92 @@ impl Receipt {
                 );
             }
         }
+        0
+    }
+
+    /// Returns the code the method exited with, which is 0 unless it exited
+    /// with a failure. This doesn't verify the receipt.
+    pub fn get_exit_code(&self) -> u32 {
+        match self.seal[8] & EXIT_FAILURE {
+            0 => 0,
+            _ => self.seal[8] & !(EXIT_FAILURE | MERKLE_JOURNAL | JOURNAL_CONTEXT),
+        }
+    }
+
+    // Returns the context at the start of the journal, if the seal says it
+    // starts with one.
+    fn get_context(&self) -> Option<Digest> {
+        if self.seal[8] & JOURNAL_CONTEXT == 0 {
+            return None;
+        }
+        let words = self.get_journal_u32();
+        let words = match self.seal[8] & MERKLE_JOURNAL {
+            0 => words.get(..DIGEST_WORDS)?,
+            _ => match words.split_first()? {
+                (&header, rest) if header == DIGEST_WORDS as u32 => rest.get(..DIGEST_WORDS)?,
+                _ => return None,
+            },
+        };
+        Some(Digest::from_slice(words))
+    }
+
+    /// Returns the entries of a journal committed as a Merkle tree. This
+    /// doesn't verify the receipt.
+    pub fn get_journal_entries(&self) -> Vec<JournalEntry> {
+        let words = self.get_journal_u32();
+        let mut entries = Vec::new();
+        let mut rest = words.as_slice();
+        while let Some((header, tail)) = rest.split_first() {
+            let (salt, tail) = match header & ENTRY_SALTED {
+                0 => (None, tail),
+                _ => {
+                    assert!(tail.len() >= DIGEST_WORDS);
+                    let (salt, tail) = tail.split_at(DIGEST_WORDS);
+                    (Some(Digest::from_slice(salt)), tail)
+                }
+            };
+            let len = (header & !ENTRY_SALTED) as usize;
+            assert!(tail.len() >= len);
+            let (data, tail) = tail.split_at(len);
+            entries.push(JournalEntry {
+                salt,
+                data: data.to_vec(),
+            });
+            rest = tail;
+        }
+        entries
+    }
+
+    /// Makes a [Disclosure] of this receipt which reveals only the journal
+    /// entries at the given indices, for a journal committed as a Merkle
+    /// tree, or `None` if any index is out of range. This doesn't verify the
+    /// receipt.
+    pub fn disclose(&self, indices: &[usize]) -> Option<Disclosure> {
+        let entries = self.get_journal_entries();
+        let tree = self.merkle_tree();
+        Some(Disclosure {
+            seal: self.seal.clone(),
+            row_size: tree.row_size() as u32,
+            entries: indices
+                .iter()
+                .map(|idx| {
+                    Some(DisclosedEntry {
+                        index: *idx as u32,
+                        entry: entries.get(*idx)?.clone(),
+                        proof: tree.prove(*idx),
+                    })
+                })
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    fn merkle_tree(&self) -> MerkleTree<risc0_zkp_core::sha::DefaultImplementation> {
+        let leaves: Vec<Digest> = self
+            .get_journal_entries()
+            .iter()
+            .map(JournalEntry::leaf)
+            .collect();
+        MerkleTree::new(risc0_zkp_core::sha::default_implementation(), &leaves)
     }
 
     pub fn get_journal_u32(&self) -> Vec<u32> {
@@ -68,9 +349,12 @@ impl Receipt {
 #[cfg(test)]
 mod tests {
     extern crate std;
-    use super::Receipt;
+    use super::{
+        JournalEntry, Receipt, DEV_MODE_SEAL, EXIT_FAILURE, JOURNAL_CONTEXT, MERKLE_JOURNAL,
+    };
     use crate::zkvm::MethodID;
-    use std::{convert::TryFrom, fs, io, vec::Vec};
+    use risc0_zkp_core::sha::{Digest, Sha};
+    use std::{convert::TryFrom, fs, io, vec, vec::Vec};
     use test_log::test;
 
     #[test]
@@ -98,6 +382,136 @@ mod tests {
         std::println!("\n");
 
         receipt.verify(&method_id);
+        assert_eq!(receipt.verify_with_exit_code(&method_id), 0);
         Ok(())
     }
+    #[test]
+    fn test_disclose() {
+        let entries = [
+            JournalEntry {
+                salt: None,
+                data: vec![1, 2, 3],
+            },
+            JournalEntry {
+                salt: Some(Digest::new([9; 8])),
+                data: vec![4],
+            },
+            JournalEntry {
+                salt: None,
+                data: vec![],
+            },
+        ];
+        let words: Vec<u32> = entries.iter().flat_map(JournalEntry::to_words).collect();
+        let journal: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        let mut receipt = Receipt {
+            journal,
+            seal: vec![0; 9],
+        };
+        assert_eq!(receipt.get_journal_entries(), entries);
+
+        let root = *receipt.merkle_tree().root();
+        receipt.seal[0..8].copy_from_slice(root.as_slice());
+        receipt.seal[8] = MERKLE_JOURNAL | (words.len() * 4) as u32;
+
+        let disclosure = receipt.disclose(&[1, 2]).unwrap();
+        disclosure.verify_entries();
+        assert_eq!(disclosure.entries[0].entry, entries[1]);
+        assert_eq!(disclosure.entries[1].index, 2);
+        assert!(receipt.disclose(&[1, 3]).is_none());
+
+        let mut forged = receipt.disclose(&[0]).unwrap();
+        forged.entries[0].entry.data[0] = 7;
+        assert!(std::panic::catch_unwind(|| forged.verify_entries()).is_err());
+
+        let mut forged = receipt.disclose(&[1]).unwrap();
+        forged.row_size = 2;
+        assert!(std::panic::catch_unwind(|| forged.verify_entries()).is_err());
+    }
+
+    #[test]
+    fn test_dev_mode() {
+        let journal = vec![1, 2, 3, 4, 5];
+        let mut seal = vec![0; 10];
+        seal[0] = 0x0403_0201;
+        seal[1] = 5;
+        seal[8] = 5;
+        seal[9] = DEV_MODE_SEAL;
+        let receipt = Receipt { journal, seal };
+        assert!(receipt.is_dev_mode());
+        let method_id =
+            MethodID::try_from(fs::read("src/zkvm/simple_receipt.id").unwrap().as_slice()).unwrap();
+        assert!(std::panic::catch_unwind(|| receipt.verify(&method_id)).is_err());
+
+        #[cfg(feature = "dev-mode")]
+        {
+            assert_eq!(receipt.verify_allowing_dev_mode(&method_id), 0);
+            let mut forged = Receipt {
+                journal: vec![9, 2, 3, 4, 5],
+                seal: receipt.seal.clone(),
+            };
+            assert!(
+                std::panic::catch_unwind(|| forged.verify_allowing_dev_mode(&method_id)).is_err()
+            );
+            forged.journal.push(6);
+            assert!(
+                std::panic::catch_unwind(|| forged.verify_allowing_dev_mode(&method_id)).is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_context() {
+        let sha = risc0_zkp_core::sha::default_implementation();
+        let context = Digest::new([1, 2, 3, 4, 5, 6, 7, 8]);
+        let words = [&context.as_slice()[..], &[9]].concat();
+        let journal: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        let mut receipt = Receipt {
+            seal: [sha.hash_bytes(&journal).as_slice(), &[journal.len() as u32]].concat(),
+            journal,
+        };
+        // A journal which merely starts with the context, as the method could
+        // commit itself, isn't made for it.
+        assert_eq!(receipt.verify_journal(), 0);
+        assert_eq!(receipt.get_context(), None);
+        receipt.seal[8] |= JOURNAL_CONTEXT;
+        assert_eq!(receipt.verify_journal(), 0);
+        assert_eq!(receipt.get_context(), Some(context));
+
+        let entries = [
+            JournalEntry {
+                salt: None,
+                data: context.as_slice().to_vec(),
+            },
+            JournalEntry {
+                salt: None,
+                data: vec![9],
+            },
+        ];
+        let words: Vec<u32> = entries.iter().flat_map(JournalEntry::to_words).collect();
+        receipt.journal = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        let root = *receipt.merkle_tree().root();
+        receipt.seal[0..8].copy_from_slice(root.as_slice());
+        receipt.seal[8] = MERKLE_JOURNAL | (words.len() * 4) as u32;
+        assert_eq!(receipt.verify_journal(), 0);
+        assert_eq!(receipt.get_context(), None);
+        receipt.seal[8] |= JOURNAL_CONTEXT;
+        assert_eq!(receipt.verify_journal(), 0);
+        assert_eq!(receipt.get_context(), Some(context));
+
+        // A journal too short to hold a context has none.
+        receipt.journal.truncate(16);
+        assert_eq!(receipt.get_context(), None);
+
+        // A failed execution keeps the flags, which say how to find the
+        // context, but not the length.
+        receipt.journal = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        receipt.seal[0..8].copy_from_slice(sha.hash_bytes(&receipt.journal).as_slice());
+        receipt.seal[8] = EXIT_FAILURE | MERKLE_JOURNAL | JOURNAL_CONTEXT | 3;
+        assert_eq!(receipt.verify_journal(), 3);
+        assert_eq!(receipt.get_exit_code(), 3);
+        assert_eq!(receipt.get_context(), Some(context));
+        receipt.seal[8] = EXIT_FAILURE | 3;
+        assert_eq!(receipt.get_exit_code(), 3);
+        assert_eq!(receipt.get_context(), None);
+    }
 }